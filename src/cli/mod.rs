@@ -2,12 +2,14 @@ use clap::Parser;
 use clap::Subcommand;
 use std::path::PathBuf;
 
+use crate::logging::LogFormat;
+
 #[derive(Debug, Parser)]
 #[clap(
     name = env!("CARGO_PKG_NAME"),
     version = env!("CARGO_PKG_VERSION"),
 )]
-pub(crate) struct Cli {
+pub struct Cli {
     #[clap(subcommand)]
     pub(crate) routine: Routine,
 
@@ -17,9 +19,63 @@ pub(crate) struct Cli {
         global = true
     )]
     pub(crate) config: PathBuf,
+
+    /// Write a JSON report of the run to this file
+    #[clap(long = "report", global = true)]
+    pub(crate) report: Option<PathBuf>,
+
+    /// Write a JSON report of the run to this ConfigMap in the config's namespace
+    #[clap(long = "report-configmap", global = true)]
+    pub(crate) report_configmap: Option<String>,
+
+    /// Export the spans of the run over OTLP/HTTP to this collector, such as
+    /// `http://tempo.monitoring:4318`
+    #[clap(
+        long = "otlp-endpoint",
+        env = "OTEL_EXPORTER_OTLP_ENDPOINT",
+        global = true
+    )]
+    pub(crate) otlp_endpoint: Option<String>,
+
+    /// Format of the log lines written to stdout
+    #[clap(long = "log-format", value_enum, default_value_t, global = true)]
+    pub(crate) log_format: LogFormat,
+
+    /// Show the state of each task in the terminal instead of the logs while the tasks run.
+    /// Ignored when stdout is not a terminal
+    #[clap(long = "progress", global = true)]
+    pub(crate) progress: bool,
+}
+
+impl Cli {
+    pub fn otlp_endpoint(&self) -> Option<&str> {
+        self.otlp_endpoint.as_deref().filter(|e| !e.is_empty())
+    }
+
+    pub fn log_format(&self) -> LogFormat {
+        self.log_format
+    }
+
+    pub fn progress(&self) -> bool {
+        self.progress
+    }
 }
 
 #[derive(Debug, Clone, Subcommand)]
 pub(crate) enum Routine {
     Daily {},
+
+    /// Restart mcservers wave by wave while keeping the mcproxy online
+    Rolling {
+        /// Number of mcservers restarted at the same time (overrides `rolling.wave_size`)
+        #[clap(long = "wave-size")]
+        wave_size: Option<usize>,
+    },
+
+    /// List the past runs recorded in the run history
+    History {
+        /// Only list the runs which touched this mcserver, with its own status
+        #[clap(long = "server")]
+        server: Option<String>,
+    },
 }
@@ -0,0 +1,53 @@
+use duration_str::deserialize_duration;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Configuration of the canary mcserver, which is relaunched and verified before the others
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub(crate) struct CanaryConfig {
+    /// Key of the mcserver relaunched first
+    pub(crate) mcserver: String,
+
+    /// Duration for which the canary pod must stay ready without restarting
+    #[serde(
+        deserialize_with = "deserialize_duration",
+        default = "default_stable_for"
+    )]
+    pub(crate) stable_for: Duration,
+
+    /// Command executed in the canary's RCON container after it became stable
+    ///
+    /// The canary fails verification if the command does not exit successfully.
+    /// Example: `["rcon-cli", "list"]`
+    #[serde(default)]
+    pub(crate) smoke_command: Option<Vec<String>>,
+}
+
+const fn default_stable_for() -> Duration {
+    Duration::from_secs(60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canary_config_deserialize_defaults() {
+        let yaml_data = r#"
+          mcserver: lobby
+        "#;
+
+        let config: CanaryConfig = serde_yaml::from_str(yaml_data).unwrap();
+
+        assert_eq!(
+            config,
+            CanaryConfig {
+                mcserver: "lobby".to_string(),
+                stable_for: Duration::from_secs(60),
+                smoke_command: None,
+            }
+        );
+    }
+}
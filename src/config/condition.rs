@@ -0,0 +1,80 @@
+use k8s_openapi::chrono::{Datelike, NaiveDate, Weekday};
+use serde::Deserialize;
+
+/// Calendar condition of a job, evaluated against the date on which the run started
+///
+/// The job runs if any of the given conditions matches, and always if none is given.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub(crate) struct JobCondition {
+    /// Weekdays on which the job runs, such as `sun` or `monday`
+    #[serde(default)]
+    pub(crate) weekdays: Vec<Weekday>,
+
+    /// Days of the month on which the job runs, from 1 to 31
+    #[serde(default)]
+    pub(crate) days_of_month: Vec<u32>,
+
+    /// The job runs every N days counted from 1970-01-01
+    ///
+    /// Days are counted on the calendar, not from the runs, so manual reruns and days without a
+    /// run do not shift the schedule.
+    pub(crate) every_n_days: Option<u32>,
+}
+
+impl JobCondition {
+    /// Whether the condition has no restriction at all
+    pub(crate) fn is_always(&self) -> bool {
+        self.weekdays.is_empty() && self.days_of_month.is_empty() && self.every_n_days.is_none()
+    }
+
+    /// Whether the job runs on `date`
+    pub(crate) fn matches(&self, date: NaiveDate) -> bool {
+        if self.is_always() {
+            return true;
+        }
+
+        let days_since_epoch = date.signed_duration_since(NaiveDate::default()).num_days();
+        self.weekdays.contains(&date.weekday())
+            || self.days_of_month.contains(&date.day())
+            || self
+                .every_n_days
+                .is_some_and(|n| n > 0 && days_since_epoch.rem_euclid(n.into()) == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_condition_matches() {
+        let condition: JobCondition = serde_yaml::from_str(
+            r#"
+weekdays: [sun]
+days_of_month: [1]
+"#,
+        )
+        .unwrap();
+
+        let sunday = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        let first = NaiveDate::from_ymd_opt(2026, 11, 1).unwrap();
+        let thursday_first = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+        assert!(condition.matches(sunday));
+        assert!(!condition.matches(monday));
+        assert!(condition.matches(first));
+        assert!(condition.matches(thursday_first));
+
+        let every_third_day = JobCondition {
+            every_n_days: Some(3),
+            ..Default::default()
+        };
+        let matching: Vec<bool> = (0..6)
+            .map(|d| every_third_day.matches(sunday + k8s_openapi::chrono::Days::new(d)))
+            .collect();
+        assert_eq!(matching.iter().filter(|m| **m).count(), 2);
+
+        assert!(JobCondition::default().matches(monday));
+    }
+}
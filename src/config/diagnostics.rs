@@ -0,0 +1,30 @@
+use serde::Deserialize;
+
+/// Configuration of diagnostics collected when a relaunch or job fails
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub(crate) struct DiagnosticsConfig {
+    /// Number of log lines collected from each container
+    #[serde(default = "default_tail_lines")]
+    pub(crate) tail_lines: i64,
+
+    /// Number of most recent events collected for each object
+    #[serde(default = "default_max_events")]
+    pub(crate) max_events: usize,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            tail_lines: default_tail_lines(),
+            max_events: default_max_events(),
+        }
+    }
+}
+
+const fn default_tail_lines() -> i64 {
+    100
+}
+const fn default_max_events() -> usize {
+    20
+}
@@ -0,0 +1,22 @@
+use serde::Deserialize;
+
+/// Configuration of the run history kept in the cluster
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub(crate) struct HistoryConfig {
+    /// ConfigMap in `namespace` holding the summaries of the last runs
+    #[serde(default = "default_configmap")]
+    pub(crate) configmap: String,
+
+    /// Number of runs kept; the oldest ones are dropped first
+    #[serde(default = "default_max_runs")]
+    pub(crate) max_runs: usize,
+}
+
+fn default_configmap() -> String {
+    "man10routine-history".to_string()
+}
+
+const fn default_max_runs() -> usize {
+    30
+}
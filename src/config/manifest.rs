@@ -0,0 +1,140 @@
+use std::path::Path;
+
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::{Api, Client};
+use tokio::fs::read_to_string;
+
+use super::ConfigLoadError;
+use super::raw::{RawConfig, RawCustomJob};
+
+impl RawConfig {
+    /// Loads `manifest_file` and `manifest_configmap` of every job into `manifest`.
+    ///
+    /// Jobs with more than one manifest source, or with `exec` or `rcon` besides one, are left
+    /// as they are and rejected while the config is parsed.
+    pub(super) async fn resolve_manifests(
+        &mut self,
+        base_dir: &Path,
+        client: &Client,
+    ) -> Result<(), ConfigLoadError> {
+        let configmap_api: Api<ConfigMap> = Api::namespaced(client.clone(), &self.namespace);
+
+        let charts = std::iter::once(&mut self.mcproxy).chain(self.mcservers.values_mut());
+        let mut jobs: Vec<&mut RawCustomJob> = Vec::new();
+        for chart in charts {
+            jobs.extend(chart.jobs_after_snapshot.values_mut());
+            jobs.extend(chart.before_shutdown.iter_mut());
+            jobs.extend(chart.after_relaunch.iter_mut());
+        }
+        jobs.extend(self.jobs.values_mut().map(|job| &mut job.job));
+
+        for job in jobs {
+            let sources = [
+                job.manifest.is_some(),
+                job.template.is_some(),
+                job.manifest_file.is_some(),
+                job.manifest_configmap.is_some(),
+                job.exec.is_some(),
+                job.rcon.is_some(),
+            ];
+            if sources.into_iter().filter(|s| *s).count() != 1 {
+                continue;
+            }
+
+            if let Some(file) = job.manifest_file.take() {
+                let path = base_dir.join(file);
+                let source = read_to_string(&path)
+                    .await
+                    .map_err(|e| ConfigLoadError::ManifestFileUnreadable(path.clone(), e))?;
+                job.manifest = Some(
+                    serde_yaml::from_str(&source)
+                        .map_err(|e| ConfigLoadError::ManifestFileInvalid(path, e))?,
+                );
+            } else if let Some(reference) = job.manifest_configmap.take() {
+                let (name, key) = reference.split_once('/').ok_or_else(|| {
+                    ConfigLoadError::ManifestConfigMapRefInvalid(reference.clone())
+                })?;
+                let configmap = configmap_api.get(name).await.map_err(|e| {
+                    ConfigLoadError::ManifestConfigMapUnavailable(reference.clone(), e)
+                })?;
+                let source = configmap
+                    .data
+                    .as_ref()
+                    .and_then(|data| data.get(key))
+                    .ok_or_else(|| {
+                        ConfigLoadError::ManifestConfigMapKeyMissing(reference.clone())
+                    })?;
+                job.manifest = Some(
+                    serde_yaml::from_str(source)
+                        .map_err(|e| ConfigLoadError::ManifestConfigMapInvalid(reference, e))?,
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, ConfigLoadError};
+    use crate::kubernetes_objects::custom_job::JobKind;
+
+    #[tokio::test]
+    async fn test_manifest_file() {
+        let dir =
+            std::env::temp_dir().join(format!("man10routine-manifest-{}", std::process::id()));
+        tokio::fs::create_dir_all(dir.join("jobs")).await.unwrap();
+        tokio::fs::write(
+            dir.join("jobs/backup.yaml"),
+            "metadata:\n  name: backup\nspec:\n  template:\n    spec:\n      containers: []\n",
+        )
+        .await
+        .unwrap();
+        let config_yaml = |manifest_file: &str| {
+            format!(
+                r#"
+namespace: "default"
+mcproxy:
+  name: "mcproxy"
+  argocd: "apps/minecraft/mcproxy"
+  rcon_container: "mcproxy"
+mcservers:
+  server1:
+    argocd: "apps/minecraft/servers/server1"
+    rcon_container: "server1"
+    jobs_after_snapshot:
+      backup:
+        manifest_file: "{manifest_file}"
+"#
+            )
+        };
+        let client =
+            Client::try_from(kube::Config::new("http://127.0.0.1:9".parse().unwrap())).unwrap();
+
+        let path = dir.join("config.yaml");
+        tokio::fs::write(&path, config_yaml("jobs/backup.yaml"))
+            .await
+            .unwrap();
+        let config = Config::new_from_file(&path, &client)
+            .await
+            .expect("Config load failed");
+        let server_1 = config.mcservers["server1"].try_read().unwrap();
+        let JobKind::KubernetesJob { manifest, .. } = &server_1.jobs_after_snapshot["backup"].kind
+        else {
+            panic!("backup job should be parsed as JobKind::KubernetesJob");
+        };
+        assert_eq!(manifest.metadata.name.as_deref(), Some("backup"));
+
+        tokio::fs::write(&path, config_yaml("jobs/missing.yaml"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            Config::new_from_file(&path, &client).await,
+            Err(ConfigLoadError::ManifestFileUnreadable(p, _)) if p == dir.join("jobs/missing.yaml")
+        ));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -0,0 +1,22 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+/// Configuration of the Prometheus metrics exported after each run
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub(crate) struct MetricsConfig {
+    /// File in the directory watched by the textfile collector of node-exporter
+    pub(crate) textfile: Option<PathBuf>,
+
+    /// Base URL of a Pushgateway such as `http://pushgateway.monitoring:9091`
+    pub(crate) pushgateway: Option<String>,
+
+    /// `job` label under which the metrics are pushed
+    #[serde(default = "default_job")]
+    pub(crate) job: String,
+}
+
+fn default_job() -> String {
+    "man10routine".to_string()
+}
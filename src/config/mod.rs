@@ -1,15 +1,34 @@
+pub mod canary;
+pub(crate) mod condition;
+pub mod diagnostics;
+pub(crate) mod history;
+mod manifest;
+pub(crate) mod metrics;
+pub(crate) mod notifications;
 pub mod polling;
 pub(crate) mod raw;
+pub mod rolling;
+pub(crate) mod template;
 
 use std::collections::BTreeMap;
 use std::iter;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
+use self::canary::CanaryConfig;
+use self::diagnostics::DiagnosticsConfig;
+use self::history::HistoryConfig;
+use self::metrics::MetricsConfig;
+use self::notifications::NotificationsConfig;
 pub use self::raw::ConfigParseError;
 use self::raw::RawConfig;
+use self::rolling::RollingConfig;
 use crate::kubernetes_objects::argocd::{ArgoCd, SharedArgoCd, WeakArgoCd};
+use crate::kubernetes_objects::custom_job::GlobalJob;
 use crate::kubernetes_objects::minecraft_chart::SharedMinecraftChart;
+use chrono_tz::Tz;
+use kube::Client;
 use thiserror::Error;
 use tokio::fs::read_to_string;
 use tokio::io;
@@ -21,6 +40,16 @@ pub(crate) struct Config {
     argocds: BTreeMap<String, SharedArgoCd>,
     pub(crate) mcproxy: SharedMinecraftChart,
     pub(crate) mcservers: BTreeMap<String, SharedMinecraftChart>,
+    pub(crate) rolling: Option<RollingConfig>,
+    pub(crate) canary: Option<CanaryConfig>,
+    pub(crate) diagnostics: DiagnosticsConfig,
+    pub(crate) successful_jobs_retention: Option<Duration>,
+    pub(crate) job_logs_dir: Option<PathBuf>,
+    pub(crate) jobs: BTreeMap<String, GlobalJob>,
+    pub(crate) time_zone: Tz,
+    pub(crate) history: Option<HistoryConfig>,
+    pub(crate) notifications: NotificationsConfig,
+    pub(crate) metrics: Option<MetricsConfig>,
 }
 
 #[derive(Error, Debug)]
@@ -33,17 +62,39 @@ pub enum ConfigLoadError {
 
     #[error("Config file {0} cannot be read: {1}")]
     FailedToRead(PathBuf, io::Error),
+
+    #[error("Job manifest file {0} cannot be read: {1}")]
+    ManifestFileUnreadable(PathBuf, io::Error),
+
+    #[error("Job manifest file {0} is not a valid Job: {1}")]
+    ManifestFileInvalid(PathBuf, serde_yaml::Error),
+
+    #[error("Job manifest ConfigMap reference '{0}' must be '<name>/<key>'")]
+    ManifestConfigMapRefInvalid(String),
+
+    #[error("Job manifest ConfigMap '{0}' cannot be read: {1}")]
+    ManifestConfigMapUnavailable(String, kube::Error),
+
+    #[error("Job manifest ConfigMap '{0}' does not have the referenced key")]
+    ManifestConfigMapKeyMissing(String),
+
+    #[error("Job manifest ConfigMap '{0}' is not a valid Job: {1}")]
+    ManifestConfigMapInvalid(String, serde_yaml::Error),
 }
 
 impl Config {
-    pub async fn new_from_file(path: &PathBuf) -> Result<Self, ConfigLoadError> {
+    /// Loads the config file; `client` is used to read job manifests from ConfigMaps.
+    pub async fn new_from_file(path: &PathBuf, client: &Client) -> Result<Self, ConfigLoadError> {
         let source_string = read_to_string(path)
             .await
             .map_err(|e| ConfigLoadError::FailedToRead(path.clone(), e))?;
 
-        let raw_config: RawConfig = serde_yaml::from_str(&source_string)
+        let mut raw_config: RawConfig = serde_yaml::from_str(&source_string)
             .map_err(|e| ConfigLoadError::ContentUnserializable(path.clone(), e))?;
 
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        raw_config.resolve_manifests(base_dir, client).await?;
+
         let config = Self::try_from(raw_config)
             .map_err(|e| ConfigLoadError::ContentInvalid(path.clone(), e))?;
 
@@ -148,6 +199,9 @@ mod tests {
 
     #[allow(unused_imports)]
     use super::*;
+    use crate::kubernetes_objects::custom_job::{JobKind, RelaunchTarget};
+    use crate::kubernetes_objects::minecraft_chart::StopMethod;
+    use std::collections::BTreeSet;
 
     #[test]
     fn test_config_parse() {
@@ -159,6 +213,10 @@ mod tests {
                 rcon_container: "mcproxy".to_string(),
                 jobs_after_snapshot: BTreeMap::new(),
                 required_to_start: None,
+                stop_command: None,
+                stop_via: None,
+                before_shutdown: vec![],
+                after_relaunch: vec![],
             },
             mcservers: BTreeMap::from([
                 (
@@ -169,6 +227,10 @@ mod tests {
                         rcon_container: "server1".to_string(),
                         jobs_after_snapshot: BTreeMap::new(),
                         required_to_start: None,
+                        stop_command: None,
+                        stop_via: None,
+                        before_shutdown: vec![],
+                        after_relaunch: vec![],
                     },
                 ),
                 (
@@ -179,9 +241,24 @@ mod tests {
                         rcon_container: "server2".to_string(),
                         jobs_after_snapshot: BTreeMap::new(),
                         required_to_start: Some(false),
+                        stop_command: None,
+                        stop_via: None,
+                        before_shutdown: vec![],
+                        after_relaunch: vec![],
                     },
                 ),
             ]),
+            rolling: None,
+            history: None,
+            metrics: None,
+            notifications: Default::default(),
+            canary: None,
+            diagnostics: Default::default(),
+            successful_jobs_retention: None,
+            job_logs_dir: None,
+            jobs: BTreeMap::new(),
+            job_templates: BTreeMap::new(),
+            time_zone: None,
         };
 
         let config = Config::try_from(raw).expect("Config parse failed");
@@ -191,12 +268,17 @@ mod tests {
             let server_1 = config.mcservers.get("server1").unwrap().try_read().unwrap();
             assert_eq!(server_1.name, "server1_customname");
             assert!(server_1.required_to_start);
+            assert_eq!(server_1.stop, StopMethod::Rcon(vec!["stop".to_string()]));
         }
         {
             let server_2 = config.mcservers.get("server2").unwrap().try_read().unwrap();
             assert_eq!(server_2.name, "server2");
             assert!(!server_2.required_to_start);
         }
+        {
+            let mcproxy = config.mcproxy.try_read().unwrap();
+            assert_eq!(mcproxy.stop, StopMethod::None);
+        }
         {
             let server_1 = config.mcservers.get("server1").unwrap().try_read().unwrap();
             let server_1_argocd = server_1.argocd.upgrade().unwrap();
@@ -236,6 +318,10 @@ mcservers:
                 rcon_container: "mcproxy".to_string(),
                 jobs_after_snapshot: BTreeMap::new(),
                 required_to_start: None,
+                stop_command: None,
+                stop_via: None,
+                before_shutdown: vec![],
+                after_relaunch: vec![],
             },
             mcservers: BTreeMap::from([
                 (
@@ -246,6 +332,10 @@ mcservers:
                         rcon_container: "server1".to_string(),
                         jobs_after_snapshot: BTreeMap::new(),
                         required_to_start: None,
+                        stop_command: None,
+                        stop_via: None,
+                        before_shutdown: vec![],
+                        after_relaunch: vec![],
                     },
                 ),
                 (
@@ -256,11 +346,475 @@ mcservers:
                         rcon_container: "server2".to_string(),
                         jobs_after_snapshot: BTreeMap::new(),
                         required_to_start: None,
+                        stop_command: None,
+                        stop_via: None,
+                        before_shutdown: vec![],
+                        after_relaunch: vec![],
                     },
                 ),
             ]),
+            rolling: None,
+            history: None,
+            metrics: None,
+            notifications: Default::default(),
+            canary: None,
+            diagnostics: Default::default(),
+            successful_jobs_retention: None,
+            job_logs_dir: None,
+            jobs: BTreeMap::new(),
+            job_templates: BTreeMap::new(),
+            time_zone: None,
         };
 
         assert_eq!(raw, expected);
     }
+
+    #[test]
+    fn test_stop_method_parse() {
+        let raw_yaml = r#"
+namespace: "default"
+mcproxy:
+  name: "mcproxy"
+  argocd: "apps/minecraft/mcproxy"
+  rcon_container: "mcproxy"
+  stop_command: ["end"]
+mcservers:
+  server1:
+    argocd: "apps/minecraft/servers/server1"
+    rcon_container: "server1"
+    stop_via: exec
+    stop_command: ["/bin/sh", "-c", "mc-send-to-console stop"]
+  server2:
+    argocd: "apps/minecraft/servers/server2"
+    rcon_container: "server2"
+    stop_via: none
+"#;
+
+        let raw: RawConfig = serde_yaml::from_str(raw_yaml).expect("YAML should deserialize");
+        let config = Config::try_from(raw).expect("Config parse failed");
+
+        let mcproxy = config.mcproxy.try_read().unwrap();
+        assert_eq!(mcproxy.stop, StopMethod::Rcon(vec!["end".to_string()]));
+        assert_eq!(
+            mcproxy.stop.argv(),
+            Some(vec!["rcon-cli".to_string(), "end".to_string()])
+        );
+
+        let server_1 = config.mcservers.get("server1").unwrap().try_read().unwrap();
+        assert_eq!(
+            server_1.stop.argv(),
+            Some(vec![
+                "/bin/sh".to_string(),
+                "-c".to_string(),
+                "mc-send-to-console stop".to_string()
+            ])
+        );
+
+        let server_2 = config.mcservers.get("server2").unwrap().try_read().unwrap();
+        assert_eq!(server_2.stop, StopMethod::None);
+        assert_eq!(server_2.stop.argv(), None);
+    }
+
+    #[test]
+    fn test_stop_command_with_stop_via_none() {
+        let raw_yaml = r#"
+namespace: "default"
+mcproxy:
+  name: "mcproxy"
+  argocd: "apps/minecraft/mcproxy"
+  rcon_container: "mcproxy"
+  stop_via: none
+  stop_command: ["end"]
+mcservers:
+  server1:
+    argocd: "apps/minecraft/servers/server1"
+    rcon_container: "server1"
+"#;
+
+        let raw: RawConfig = serde_yaml::from_str(raw_yaml).expect("YAML should deserialize");
+        assert!(matches!(
+            Config::try_from(raw),
+            Err(ConfigParseError::StopCommandWithStopViaNone { .. })
+        ));
+    }
+
+    #[test]
+    fn test_canary_mcserver_not_found() {
+        let raw_yaml = r#"
+namespace: "default"
+mcproxy:
+  name: "mcproxy"
+  argocd: "apps/minecraft/mcproxy"
+  rcon_container: "mcproxy"
+mcservers:
+  server1:
+    argocd: "apps/minecraft/servers/server1"
+    rcon_container: "server1"
+canary:
+  mcserver: "server2"
+"#;
+
+        let raw: RawConfig = serde_yaml::from_str(raw_yaml).expect("YAML should deserialize");
+        assert!(matches!(
+            Config::try_from(raw),
+            Err(ConfigParseError::CanaryMcserverNotFound { name }) if name == "server2"
+        ));
+    }
+
+    #[test]
+    fn test_global_jobs_parse() {
+        let raw_yaml = r#"
+namespace: "default"
+mcproxy:
+  name: "mcproxy"
+  argocd: "apps/minecraft/mcproxy"
+  rcon_container: "mcproxy"
+mcservers:
+  lobby:
+    argocd: "apps/minecraft/servers/lobby"
+    rcon_container: "lobby"
+    jobs_after_snapshot:
+      backup:
+        manifest: {}
+  survival:
+    argocd: "apps/minecraft/servers/survival"
+    rcon_container: "survival"
+jobs:
+  db-dump:
+    dependencies: ["phase:all_servers_down"]
+    manifest: {}
+  upload:
+    dependencies: ["mcserver:lobby/backup"]
+    manifest: {}
+    retries: 2
+  notify:
+    dependencies: ["job:upload"]
+    blocks_relaunch: ["mcproxy"]
+    manifest: {}
+  z-upload:
+    dependencies: ["job:db-dump"]
+    manifest: {}
+"#;
+
+        let raw: RawConfig = serde_yaml::from_str(raw_yaml).expect("YAML should deserialize");
+        let config = Config::try_from(raw).expect("Config parse failed");
+
+        let lobby = RelaunchTarget::Mcserver("lobby".to_string());
+        let survival = RelaunchTarget::Mcserver("survival".to_string());
+        assert_eq!(
+            config.jobs["db-dump"].blocks_relaunch,
+            BTreeSet::from([lobby.clone(), survival, RelaunchTarget::Mcproxy])
+        );
+        assert_eq!(config.jobs["upload"].job.retries, 2);
+        assert_eq!(
+            config.jobs["upload"].blocks_relaunch,
+            BTreeSet::from([lobby])
+        );
+        assert_eq!(
+            config.jobs["notify"].blocks_relaunch,
+            BTreeSet::from([RelaunchTarget::Mcproxy])
+        );
+        // Inherited from a job sorted before it
+        assert_eq!(
+            config.jobs["z-upload"].blocks_relaunch,
+            config.jobs["db-dump"].blocks_relaunch
+        );
+    }
+
+    #[test]
+    fn test_global_jobs_invalid() {
+        let config_with_jobs = |jobs: &str| {
+            let raw_yaml = format!(
+                r#"
+namespace: "default"
+mcproxy:
+  name: "mcproxy"
+  argocd: "apps/minecraft/mcproxy"
+  rcon_container: "mcproxy"
+mcservers:
+  lobby:
+    argocd: "apps/minecraft/servers/lobby"
+    rcon_container: "lobby"
+jobs:
+{jobs}"#
+            );
+            let raw: RawConfig = serde_yaml::from_str(&raw_yaml).expect("YAML should deserialize");
+            Config::try_from(raw)
+        };
+
+        assert!(matches!(
+            config_with_jobs("  a:\n    dependencies: [\"lobby/backup\"]\n    manifest: {}\n"),
+            Err(ConfigParseError::InvalidJobReference { .. })
+        ));
+        assert!(matches!(
+            config_with_jobs(
+                "  a:\n    dependencies: [\"mcserver:lobby/backup\"]\n    manifest: {}\n"
+            ),
+            Err(ConfigParseError::JobReferenceNotFound { .. })
+        ));
+        assert!(matches!(
+            config_with_jobs(
+                "  a:\n    dependencies: [\"job:b\"]\n    manifest: {}\n  b:\n    dependencies: [\"job:a\"]\n    manifest: {}\n"
+            ),
+            Err(ConfigParseError::JobDependencyCycle { cycle }) if cycle == "a -> b -> a"
+        ));
+        assert!(matches!(
+            config_with_jobs("  a:\n    blocks_relaunch: [\"mcserver:hub\"]\n    manifest: {}\n"),
+            Err(ConfigParseError::InvalidRelaunchTarget { .. })
+        ));
+    }
+
+    #[test]
+    fn test_hooks_parse() {
+        let raw_yaml = r#"
+namespace: "default"
+mcproxy:
+  name: "mcproxy"
+  argocd: "apps/minecraft/mcproxy"
+  rcon_container: "mcproxy"
+mcservers:
+  server1:
+    argocd: "apps/minecraft/servers/server1"
+    rcon_container: "server1"
+    before_shutdown:
+      - exec:
+          command: ["/bin/sh", "-c", "export-db"]
+      - manifest:
+          spec:
+            template:
+              spec:
+                containers:
+                  - name: export
+                    image: busybox
+        required: false
+    after_relaunch:
+      - rcon:
+          pod: "mcproxy-0"
+          commands:
+            - command: "gamerule keepInventory true"
+        required: false
+"#;
+
+        let raw: RawConfig = serde_yaml::from_str(raw_yaml).expect("YAML should deserialize");
+        let config = Config::try_from(raw).expect("Config parse failed");
+
+        let server_1 = config.mcservers.get("server1").unwrap().try_read().unwrap();
+        let before_shutdown = &server_1.hooks.before_shutdown;
+        assert_eq!(before_shutdown.len(), 2);
+        assert!(before_shutdown[0].required);
+        let JobKind::Exec(step) = &before_shutdown[0].kind else {
+            panic!("exec hook should be parsed as JobKind::Exec");
+        };
+        assert_eq!(
+            (step.pod.as_str(), step.container.as_str()),
+            ("server1-0", "server1")
+        );
+        assert!(!before_shutdown[1].required);
+        assert!(matches!(
+            before_shutdown[1].kind,
+            JobKind::KubernetesJob { .. }
+        ));
+
+        let after_relaunch = &server_1.hooks.after_relaunch;
+        assert!(!after_relaunch[0].required);
+        let JobKind::Rcon(step) = &after_relaunch[0].kind else {
+            panic!("rcon hook should be parsed as JobKind::Rcon");
+        };
+        assert_eq!(
+            (step.pod.as_str(), step.container.as_str()),
+            ("mcproxy-0", "server1")
+        );
+        assert_eq!(step.commands[0].command, "gamerule keepInventory true");
+        drop(server_1);
+
+        // Config errors name the hooks the same way as the routine does
+        let raw_yaml = raw_yaml.replace(r#"- command: "gamerule keepInventory true""#, "[]");
+        let raw: RawConfig = serde_yaml::from_str(&raw_yaml).expect("YAML should deserialize");
+        assert!(matches!(
+            Config::try_from(raw),
+            Err(ConfigParseError::RconJobCommandsEmpty { job_name, .. }) if job_name == "after-relaunch-1"
+        ));
+    }
+
+    #[test]
+    fn test_job_templates() {
+        let config_with_backup_job = |job: &str| {
+            let raw_yaml = format!(
+                r#"
+namespace: "minecraft"
+mcproxy:
+  name: "mcproxy"
+  argocd: "apps/minecraft/mcproxy"
+  rcon_container: "mcproxy"
+mcservers:
+  lobby:
+    name: "mcserver-lobby"
+    argocd: "apps/minecraft/servers/lobby"
+    rcon_container: "mcserver"
+    jobs_after_snapshot:
+      backup:
+{job}
+job_templates:
+  backup:
+    manifest:
+      metadata:
+        namespace: "{{{{namespace}}}}"
+      spec:
+        template:
+          spec:
+            containers:
+              - name: backup
+                image: restic
+                args: ["backup", "--tag", "{{{{chart_name}}}}-{{{{date}}}}"]
+            volumes:
+              - name: data
+                persistentVolumeClaim:
+                  claimName: "{{{{pvc}}}}"
+"#
+            );
+            let raw: RawConfig = serde_yaml::from_str(&raw_yaml).expect("YAML should deserialize");
+            Config::try_from(raw)
+        };
+
+        let config = config_with_backup_job(
+            "        template: backup\n        variables:\n          pvc: data-mcserver-lobby-0",
+        )
+        .expect("Config parse failed");
+        let lobby = config.mcservers["lobby"].try_read().unwrap();
+        let job = &lobby.jobs_after_snapshot["backup"];
+        let JobKind::KubernetesJob {
+            manifest,
+            templated: true,
+        } = &job.kind
+        else {
+            panic!("templated job should be parsed as JobKind::KubernetesJob");
+        };
+        assert_eq!(manifest.metadata.namespace.as_deref(), Some("minecraft"));
+        let pod_spec = manifest
+            .spec
+            .as_ref()
+            .unwrap()
+            .template
+            .spec
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            pod_spec.containers[0].args.as_ref().unwrap()[2],
+            "lobby-{{date}}"
+        );
+        assert_eq!(
+            pod_spec.volumes.as_ref().unwrap()[0]
+                .persistent_volume_claim
+                .as_ref()
+                .unwrap()
+                .claim_name,
+            "data-mcserver-lobby-0"
+        );
+
+        assert!(matches!(
+            config_with_backup_job("        template: backup"),
+            Err(ConfigParseError::JobTemplateSubstitution { .. })
+        ));
+        assert!(matches!(
+            config_with_backup_job("        template: restore"),
+            Err(ConfigParseError::JobTemplateNotFound { .. })
+        ));
+        assert!(matches!(
+            config_with_backup_job("        required: false"),
+            Err(ConfigParseError::JobManifestMissing { .. })
+        ));
+        assert!(matches!(
+            config_with_backup_job(
+                "        manifest_file: jobs/backup.yaml\n        manifest_configmap: jobs/backup"
+            ),
+            Err(ConfigParseError::JobManifestConflict { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pod_jobs() {
+        let config_with_job = |job: &str| {
+            let raw_yaml = format!(
+                r#"
+namespace: "minecraft"
+mcproxy:
+  name: "mcproxy"
+  argocd: "apps/minecraft/mcproxy"
+  rcon_container: "mcproxy"
+mcservers:
+  lobby:
+    name: "mcserver-lobby"
+    argocd: "apps/minecraft/servers/lobby"
+    rcon_container: "mcserver"
+    jobs_after_snapshot:
+      notify:
+{job}
+"#
+            );
+            let raw: RawConfig = serde_yaml::from_str(&raw_yaml).expect("YAML should deserialize");
+            Config::try_from(raw)
+        };
+
+        let config = config_with_job(
+            r#"        exec:
+          pod: "mcserver-hub-0"
+          container: "mcserver"
+          command: ["touch", "/data/lobby-snapshot-done"]"#,
+        )
+        .expect("Config parse failed");
+        let lobby = config.mcservers["lobby"].try_read().unwrap();
+        let JobKind::Exec(step) = &lobby.jobs_after_snapshot["notify"].kind else {
+            panic!("exec job should be parsed as JobKind::Exec");
+        };
+        assert_eq!(step.pod, "mcserver-hub-0");
+        assert_eq!(step.command.len(), 2);
+        drop(lobby);
+
+        let config = config_with_job(
+            r#"        required: false
+        rcon:
+          pod: "mcserver-hub-0"
+          container: "mcserver"
+          commands:
+            - command: "save-all flush"
+              expect_output: "Saved the game"
+            - command: "say Lobby snapshot done""#,
+        )
+        .expect("Config parse failed");
+        let lobby = config.mcservers["lobby"].try_read().unwrap();
+        let job = &lobby.jobs_after_snapshot["notify"];
+        assert!(!job.required);
+        let JobKind::Rcon(step) = &job.kind else {
+            panic!("rcon job should be parsed as JobKind::Rcon");
+        };
+        assert_eq!(
+            step.commands[0].expect_output.as_deref(),
+            Some("Saved the game")
+        );
+        assert_eq!(step.commands[1].expect_output, None);
+        drop(lobby);
+
+        assert!(matches!(
+            config_with_job(
+                "        exec:\n          pod: \"mcserver-hub-0\"\n          container: \"mcserver\"\n          command: []"
+            ),
+            Err(ConfigParseError::ExecJobCommandEmpty { .. })
+        ));
+        assert!(matches!(
+            config_with_job(
+                "        rcon:\n          pod: \"mcserver-hub-0\"\n          container: \"mcserver\"\n          commands: []"
+            ),
+            Err(ConfigParseError::RconJobCommandsEmpty { .. })
+        ));
+        assert!(matches!(
+            config_with_job("        exec:\n          command: [\"true\"]"),
+            Err(ConfigParseError::JobPodMissing { .. })
+        ));
+        assert!(matches!(
+            config_with_job(
+                "        manifest: {}\n        exec:\n          pod: \"mcserver-hub-0\"\n          container: \"mcserver\"\n          command: [\"true\"]"
+            ),
+            Err(ConfigParseError::JobManifestConflict { .. })
+        ));
+    }
 }
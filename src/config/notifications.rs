@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use duration_str::deserialize_duration;
+use serde::{Deserialize, Serialize};
+
+/// Configuration of the messages posted about each run
+#[derive(Debug, Clone, Default, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub(crate) struct NotificationsConfig {
+    /// Destinations of the messages
+    #[serde(default)]
+    pub(crate) sinks: Vec<SinkConfig>,
+
+    /// An alert is posted when restoring an ArgoCD Application takes longer than this
+    #[serde(
+        deserialize_with = "deserialize_duration",
+        default = "default_restore_alert_after"
+    )]
+    pub(crate) restore_alert_after: Duration,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub(crate) struct SinkConfig {
+    #[serde(flatten)]
+    pub(crate) kind: SinkKind,
+
+    /// Kinds of messages posted to the sink, all of them by default
+    #[serde(default = "default_events")]
+    pub(crate) events: Vec<NotificationKind>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum SinkKind {
+    /// Discord webhook
+    Discord { url: String },
+
+    /// Slack incoming webhook
+    Slack { url: String },
+
+    /// Any endpoint accepting the notification as JSON
+    Webhook {
+        url: String,
+
+        /// Additional headers such as `Authorization`
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+impl SinkKind {
+    pub(crate) fn url(&self) -> &str {
+        match self {
+            SinkKind::Discord { url } | SinkKind::Slack { url } | SinkKind::Webhook { url, .. } => {
+                url
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum NotificationKind {
+    /// The run has started
+    Start,
+
+    /// The run has finished, successfully or not
+    Summary,
+
+    /// Something failed or is stuck while the run is going on
+    Alert,
+}
+
+fn default_restore_alert_after() -> Duration {
+    Duration::from_secs(5 * 60)
+}
+
+fn default_events() -> Vec<NotificationKind> {
+    vec![
+        NotificationKind::Start,
+        NotificationKind::Summary,
+        NotificationKind::Alert,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notifications_config_deserialize() {
+        let yaml_data = r#"
+          sinks:
+            - type: discord
+              url: "https://discord.com/api/webhooks/1/token"
+            - type: webhook
+              url: "http://alertmanager.monitoring:9093/hook"
+              headers:
+                Authorization: "Bearer secret"
+              events: [alert]
+        "#;
+
+        let config: NotificationsConfig = serde_yaml::from_str(yaml_data).unwrap();
+
+        assert_eq!(config.restore_alert_after, Duration::from_secs(300));
+        assert_eq!(config.sinks[0].events.len(), 3);
+        assert_eq!(config.sinks[1].events, vec![NotificationKind::Alert]);
+        assert!(matches!(
+            &config.sinks[1].kind,
+            SinkKind::Webhook { headers, .. } if headers["Authorization"] == "Bearer secret"
+        ));
+    }
+}
@@ -32,6 +32,13 @@ pub(crate) struct PollingConfig {
 
     #[serde(default = "default_max_errors")]
     pub(crate) max_errors: u64,
+
+    /// Whether to follow the object with a watch instead of polling it
+    ///
+    /// While watching, `poll_interval` is the interval at which the last state is re-evaluated
+    /// and `initial_wait` is not used. Polling is used as a fallback when the watch breaks.
+    #[serde(default = "default_watch")]
+    pub(crate) watch: bool,
 }
 
 impl Default for PollingConfig {
@@ -42,6 +49,7 @@ impl Default for PollingConfig {
             max_wait: Duration::from_secs(600),
             error_wait: Duration::from_secs(10),
             max_errors: 5,
+            watch: true,
         }
     }
 }
@@ -61,6 +69,9 @@ const fn default_error_wait() -> Duration {
 const fn default_max_errors() -> u64 {
     5
 }
+const fn default_watch() -> bool {
+    true
+}
 
 #[cfg(test)]
 mod tests {
@@ -89,6 +100,7 @@ mod tests {
                 max_wait: Duration::from_secs(600),
                 error_wait: Duration::from_secs(10),
                 max_errors: 5,
+                watch: true,
             }
         );
     }
@@ -107,6 +119,7 @@ mod tests {
                 max_wait: Duration::from_secs(600),
                 error_wait: Duration::from_secs(10),
                 max_errors: 5,
+                watch: true,
             }
         );
     }
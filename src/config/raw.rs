@@ -1,10 +1,28 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter;
+use std::path::PathBuf;
+use std::time::Duration;
 
 use super::Config;
+use super::canary::CanaryConfig;
+use super::condition::JobCondition;
+use super::diagnostics::DiagnosticsConfig;
+use super::history::HistoryConfig;
+use super::metrics::MetricsConfig;
+use super::notifications::NotificationsConfig;
 use super::polling::PollingConfig;
+use super::rolling::RollingConfig;
+use super::template::{JobTemplate, SubstitutionError, is_runtime_variable, substitute_value};
+use crate::http::is_http_url;
 use crate::kubernetes_objects::argocd::SharedArgoCd;
-use crate::kubernetes_objects::custom_job::CustomJob;
-use crate::kubernetes_objects::minecraft_chart::MinecraftChart;
+use crate::kubernetes_objects::custom_job::{
+    CustomJob, ExecStep, GlobalJob, JobKind, JobRef, RconCommand, RconStep, RelaunchTarget,
+    RetryOn, RoutinePhase,
+};
+use crate::kubernetes_objects::hook::{ChartHooks, HookPoint};
+use crate::kubernetes_objects::minecraft_chart::{MinecraftChart, StopMethod};
+use chrono_tz::Tz;
+use duration_str::{deserialize_duration, deserialize_option_duration};
 use k8s_openapi::api::batch::v1::Job;
 use serde::Deserialize;
 use thiserror::Error;
@@ -15,6 +33,44 @@ pub(super) struct RawConfig {
     pub(super) namespace: String,
     pub(super) mcproxy: RawMinecraftChart,
     pub(super) mcservers: BTreeMap<String, RawMinecraftChart>,
+
+    /// Rolling restart routine configuration
+    pub(super) rolling: Option<RollingConfig>,
+
+    /// mcserver relaunched and verified before all the others
+    pub(super) canary: Option<CanaryConfig>,
+
+    /// Diagnostics collected when a relaunch or job fails
+    #[serde(default)]
+    pub(super) diagnostics: DiagnosticsConfig,
+
+    /// Successful Jobs created by the routine are deleted after this period
+    #[serde(default, deserialize_with = "deserialize_option_duration")]
+    pub(super) successful_jobs_retention: Option<Duration>,
+
+    /// Directory to which the logs of each custom job are saved as `<job name>.log`
+    pub(super) job_logs_dir: Option<PathBuf>,
+
+    /// Jobs not tied to a single chart, run by the daily routine
+    #[serde(default)]
+    pub(super) jobs: BTreeMap<String, RawGlobalJob>,
+
+    /// Job manifests shared by several charts, referenced by `template`
+    #[serde(default)]
+    pub(super) job_templates: BTreeMap<String, JobTemplate>,
+
+    /// Time zone in which the `when` conditions of jobs are evaluated, UTC by default
+    pub(super) time_zone: Option<Tz>,
+
+    /// Run history kept in a ConfigMap, read by the `history` command
+    pub(super) history: Option<HistoryConfig>,
+
+    /// Messages posted to Discord, Slack or webhooks about each run
+    #[serde(default)]
+    pub(super) notifications: NotificationsConfig,
+
+    /// Prometheus metrics written to a textfile or pushed to a Pushgateway after each run
+    pub(super) metrics: Option<MetricsConfig>,
 }
 
 #[cfg_attr(test, derive(PartialEq))]
@@ -37,6 +93,43 @@ pub(super) struct RawMinecraftChart {
 
     /// Whether this chart is required to restart the mcproxy
     pub(super) required_to_start: Option<bool>,
+
+    /// Command used to stop the server gracefully
+    ///
+    /// Interpreted according to `stop_via`.
+    /// Example: `["stop"]` for mcservers, `["end"]` for Velocity/BungeeCord
+    pub(super) stop_command: Option<Vec<String>>,
+
+    /// How `stop_command` is delivered to the server
+    ///
+    /// Defaults to `rcon` when `stop_command` is set.
+    /// Otherwise, mcservers default to `rcon stop` and mcproxy to `none`.
+    pub(super) stop_via: Option<RawStopVia>,
+
+    /// Jobs run in order while the server is still up, before it is stopped
+    ///
+    /// `dependencies` are ignored, and `exec` and `rcon` default to `rcon_container` of the
+    /// chart's pod.
+    #[serde(default)]
+    pub(super) before_shutdown: Vec<RawCustomJob>,
+
+    /// Jobs run in order once the server has been relaunched and is ready, as `before_shutdown`
+    #[serde(default)]
+    pub(super) after_relaunch: Vec<RawCustomJob>,
+}
+
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum RawStopVia {
+    /// Execute `stop_command` as argv in `rcon_container`
+    Exec,
+
+    /// Send `stop_command` through `rcon-cli` in `rcon_container`
+    Rcon,
+
+    /// Only scale the StatefulSet down without a graceful stop command
+    None,
 }
 
 #[cfg_attr(test, derive(PartialEq))]
@@ -46,8 +139,27 @@ pub(super) struct RawCustomJob {
     #[serde(default)]
     pub(super) dependencies: Vec<String>,
 
-    /// Kubernetes Job YAML
-    pub(super) manifest: Job,
+    /// Kubernetes Job YAML; exclusive with the other manifest sources
+    pub(super) manifest: Option<Job>,
+
+    /// File containing the Kubernetes Job YAML, relative to the config file
+    pub(super) manifest_file: Option<PathBuf>,
+
+    /// ConfigMap key containing the Kubernetes Job YAML, as `<name>/<key>`
+    pub(super) manifest_configmap: Option<String>,
+
+    /// Name of a `job_templates` entry from which the manifest is built
+    pub(super) template: Option<String>,
+
+    /// Command executed in a running pod instead of a Kubernetes Job
+    pub(super) exec: Option<RawExecStep>,
+
+    /// RCON commands sent in a running pod instead of a Kubernetes Job
+    pub(super) rcon: Option<RawRconStep>,
+
+    /// Variables substituted in the template in addition to the built-in ones
+    #[serde(default)]
+    pub(super) variables: BTreeMap<String, String>,
 
     /// Whether the job's successful completion is required to continue routine or not
     #[serde(default = "default_required")]
@@ -56,12 +168,71 @@ pub(super) struct RawCustomJob {
     /// Polling configuration for waiting for job completion
     #[serde(default)]
     pub(super) completion_polling: PollingConfig,
+
+    /// Number of times the job is recreated after a failed attempt
+    #[serde(default)]
+    pub(super) retries: u32,
+
+    /// Wait before the first retry, doubled on every further retry
+    #[serde(
+        deserialize_with = "deserialize_duration",
+        default = "default_retry_backoff"
+    )]
+    pub(super) retry_backoff: Duration,
+
+    /// Kinds of failures which are retried
+    #[serde(default = "default_retry_on")]
+    pub(super) retry_on: Vec<RetryOn>,
+
+    /// Calendar condition on which the job runs; skipped jobs still satisfy dependencies
+    #[serde(default)]
+    pub(super) when: JobCondition,
+}
+
+/// [`ExecStep`] whose pod and container may be left to the chart of a hook
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Deserialize, Debug, Clone)]
+pub(super) struct RawExecStep {
+    pub(super) pod: Option<String>,
+    pub(super) container: Option<String>,
+    pub(super) command: Vec<String>,
+}
+
+/// [`RconStep`] whose pod and container may be left to the chart of a hook
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Deserialize, Debug, Clone)]
+pub(super) struct RawRconStep {
+    pub(super) pod: Option<String>,
+    pub(super) container: Option<String>,
+    pub(super) commands: Vec<RconCommand>,
+}
+
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Deserialize, Debug, Clone)]
+pub(super) struct RawGlobalJob {
+    /// Same fields as chart jobs, but `dependencies` are references such as
+    /// `mcserver:lobby/backup`, `mcserver:lobby`, `job:db-dump` or `phase:all_servers_down`
+    #[serde(flatten)]
+    pub(super) job: RawCustomJob,
+
+    /// Charts whose relaunch waits for this job, as `mcserver:<mcserver>` or `mcproxy`
+    ///
+    /// Defaults to every chart which the job requires to be shut down.
+    pub(super) blocks_relaunch: Option<Vec<String>>,
 }
 
 const fn default_required() -> bool {
     true
 }
 
+const fn default_retry_backoff() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_retry_on() -> Vec<RetryOn> {
+    vec![RetryOn::Failure, RetryOn::Timeout]
+}
+
 #[derive(Error, Debug)]
 pub enum ConfigParseError {
     #[error(
@@ -88,11 +259,132 @@ pub enum ConfigParseError {
     #[error("Not all 'required_to_start' values for all mcservers should be false.")]
     McproxyRequiresNoServerToStart,
 
+    #[error("'stop_command' of chart '{chart_name}' must not be empty")]
+    StopCommandEmpty { chart_name: String },
+
+    #[error("'stop_command' cannot be set for chart '{chart_name}' because 'stop_via' is 'none'")]
+    StopCommandWithStopViaNone { chart_name: String },
+
+    #[error("'rolling.wave_size' must be at least 1")]
+    RollingWaveSizeZero,
+
+    #[error("'history.max_runs' must be at least 1")]
+    HistoryMaxRunsZero,
+
+    #[error("Notification sink URL '{0}' is not a valid http(s) URL")]
+    NotificationUrlInvalid(String),
+
+    #[error("'metrics.pushgateway' URL '{0}' is not a valid http(s) URL")]
+    PushgatewayUrlInvalid(String),
+
+    #[error("'rolling.move_players_command' must not be empty")]
+    RollingMovePlayersCommandEmpty,
+
+    #[error("Canary mcserver '{name}' is not defined in 'mcservers'")]
+    CanaryMcserverNotFound { name: String },
+
+    #[error("'canary.smoke_command' must not be empty")]
+    CanarySmokeCommandEmpty,
+
     #[error("Job name '{job_name}' in chart '{chart_name}' must not contain '/' characters")]
     JobNameIncludesSlash {
         chart_name: String,
         job_name: String,
     },
+
+    #[error(
+        "Job '{job_name}' of chart '{chart_name}' must have one of 'manifest', 'manifest_file', 'manifest_configmap', 'template', 'exec' or 'rcon'"
+    )]
+    JobManifestMissing {
+        chart_name: String,
+        job_name: String,
+    },
+
+    #[error(
+        "Job '{job_name}' of chart '{chart_name}' must have only one of 'manifest', 'manifest_file', 'manifest_configmap', 'template', 'exec' and 'rcon'"
+    )]
+    JobManifestConflict {
+        chart_name: String,
+        job_name: String,
+    },
+
+    #[error("Command of exec job '{job_name}' of chart '{chart_name}' must not be empty")]
+    ExecJobCommandEmpty {
+        chart_name: String,
+        job_name: String,
+    },
+
+    #[error("RCON job '{job_name}' of chart '{chart_name}' must have at least one command")]
+    RconJobCommandsEmpty {
+        chart_name: String,
+        job_name: String,
+    },
+
+    #[error(
+        "Job '{job_name}' of chart '{chart_name}' must set 'pod' and 'container' of its 'exec' or 'rcon' step"
+    )]
+    JobPodMissing {
+        chart_name: String,
+        job_name: String,
+    },
+
+    #[error(
+        "Job template '{template}' used by job '{job_name}' of chart '{chart_name}' does not exist"
+    )]
+    JobTemplateNotFound {
+        chart_name: String,
+        job_name: String,
+        template: String,
+    },
+
+    #[error(
+        "Job template for job '{job_name}' of chart '{chart_name}' cannot be substituted: {error}"
+    )]
+    JobTemplateSubstitution {
+        chart_name: String,
+        job_name: String,
+        error: SubstitutionError,
+    },
+
+    #[error(
+        "Job template for job '{job_name}' of chart '{chart_name}' is not a valid Job: {error}"
+    )]
+    JobTemplateInvalid {
+        chart_name: String,
+        job_name: String,
+        error: serde_yaml::Error,
+    },
+
+    #[error(
+        "'when.days_of_month' of job '{job_name}' of chart '{chart_name}' must be between 1 and 31"
+    )]
+    JobConditionDayOfMonthInvalid {
+        chart_name: String,
+        job_name: String,
+    },
+
+    #[error("'when.every_n_days' of job '{job_name}' of chart '{chart_name}' must be at least 1")]
+    JobConditionEveryNDaysZero {
+        chart_name: String,
+        job_name: String,
+    },
+
+    #[error("Top-level job name '{job_name}' must not contain '/' characters")]
+    GlobalJobNameIncludesSlash { job_name: String },
+
+    #[error("Dependency '{reference}' of top-level job '{job_name}' is not a valid reference")]
+    InvalidJobReference { job_name: String, reference: String },
+
+    #[error("Dependency '{reference}' of top-level job '{job_name}' does not exist")]
+    JobReferenceNotFound { job_name: String, reference: String },
+
+    #[error("Top-level jobs depend on each other: {cycle}")]
+    JobDependencyCycle { cycle: String },
+
+    #[error(
+        "'blocks_relaunch' entry '{target}' of top-level job '{job_name}' is not a known chart"
+    )]
+    InvalidRelaunchTarget { job_name: String, target: String },
 }
 
 impl TryFrom<RawConfig> for Config {
@@ -118,20 +410,89 @@ impl TryFrom<RawConfig> for Config {
             return Err(ConfigParseError::McproxyRequiresNoServerToStart);
         }
 
+        if let Some(rolling) = &raw.rolling {
+            if rolling.wave_size == 0 {
+                return Err(ConfigParseError::RollingWaveSizeZero);
+            }
+            if rolling.move_players_command.is_empty() {
+                return Err(ConfigParseError::RollingMovePlayersCommandEmpty);
+            }
+        }
+
+        if raw.history.as_ref().is_some_and(|h| h.max_runs == 0) {
+            return Err(ConfigParseError::HistoryMaxRunsZero);
+        }
+
+        for sink in &raw.notifications.sinks {
+            let url = sink.kind.url();
+            if !is_http_url(url) {
+                return Err(ConfigParseError::NotificationUrlInvalid(url.to_string()));
+            }
+        }
+
+        if let Some(url) = raw.metrics.as_ref().and_then(|m| m.pushgateway.as_deref())
+            && !is_http_url(url)
+        {
+            return Err(ConfigParseError::PushgatewayUrlInvalid(url.to_string()));
+        }
+
+        if let Some(canary) = &raw.canary {
+            if !raw.mcservers.contains_key(&canary.mcserver) {
+                return Err(ConfigParseError::CanaryMcserverNotFound {
+                    name: canary.mcserver.clone(),
+                });
+            }
+            if canary.smoke_command.as_ref().is_some_and(|c| c.is_empty()) {
+                return Err(ConfigParseError::CanarySmokeCommandEmpty);
+            }
+        }
+
+        let templates = &raw.job_templates;
+        let jobs = Self::build_global_jobs(
+            raw.jobs,
+            &raw.mcservers,
+            &TemplateScope::global(templates, &raw.namespace),
+        )?;
+
         let namespace = raw.namespace;
         let mcproxy_argocd = Self::build_argocd_hierarchy(&mut argocds, &raw.mcproxy.argocd)?;
         let mcproxy_name = raw
             .mcproxy
             .name
             .ok_or(ConfigParseError::McproxyNameMissing)?;
+        let mcproxy_scope = TemplateScope::chart(templates, &namespace, "mcproxy", &mcproxy_name);
         let mcproxy_jobs =
-            Self::build_jobs_after_snapshot(raw.mcproxy.jobs_after_snapshot, &mcproxy_name)?;
+            Self::build_jobs_after_snapshot(raw.mcproxy.jobs_after_snapshot, &mcproxy_scope)?;
+        let mcproxy_stop = Self::build_stop_method(
+            raw.mcproxy.stop_via,
+            raw.mcproxy.stop_command,
+            RawStopVia::None,
+            &mcproxy_name,
+        )?;
+        let mcproxy_hooks = ChartHooks {
+            before_shutdown: Self::build_hooks(
+                raw.mcproxy.before_shutdown,
+                HookPoint::BeforeShutdown,
+                &mcproxy_scope,
+                &mcproxy_name,
+                &raw.mcproxy.rcon_container,
+            )?,
+            after_relaunch: Self::build_hooks(
+                raw.mcproxy.after_relaunch,
+                HookPoint::AfterRelaunch,
+                &mcproxy_scope,
+                &mcproxy_name,
+                &raw.mcproxy.rcon_container,
+            )?,
+        };
         let mcproxy = MinecraftChart::new(
             mcproxy_name,
             mcproxy_argocd,
             raw.mcproxy.rcon_container,
             mcproxy_jobs,
             false,
+            mcproxy_stop,
+            mcproxy_hooks,
         );
         let mcservers = raw
             .mcservers
@@ -139,14 +500,39 @@ impl TryFrom<RawConfig> for Config {
             .map(|(name, server)| {
                 let server_argocd = Self::build_argocd_hierarchy(&mut argocds, &server.argocd)?;
                 let server_name = server.name.unwrap_or_else(|| name.clone());
+                let scope = TemplateScope::chart(templates, &namespace, &name, &server_name);
                 let jobs_after_snapshot =
-                    Self::build_jobs_after_snapshot(server.jobs_after_snapshot, &server_name)?;
+                    Self::build_jobs_after_snapshot(server.jobs_after_snapshot, &scope)?;
+                let stop = Self::build_stop_method(
+                    server.stop_via,
+                    server.stop_command,
+                    RawStopVia::Rcon,
+                    &server_name,
+                )?;
+                let hooks = ChartHooks {
+                    before_shutdown: Self::build_hooks(
+                        server.before_shutdown,
+                        HookPoint::BeforeShutdown,
+                        &scope,
+                        &server_name,
+                        &server.rcon_container,
+                    )?,
+                    after_relaunch: Self::build_hooks(
+                        server.after_relaunch,
+                        HookPoint::AfterRelaunch,
+                        &scope,
+                        &server_name,
+                        &server.rcon_container,
+                    )?,
+                };
                 let mc_chart = MinecraftChart::new(
                     server_name,
                     server_argocd,
                     server.rcon_container,
                     jobs_after_snapshot,
                     server.required_to_start.unwrap_or(true),
+                    stop,
+                    hooks,
                 );
                 Ok((name, mc_chart))
             })
@@ -157,34 +543,403 @@ impl TryFrom<RawConfig> for Config {
             argocds,
             mcproxy,
             mcservers,
+            rolling: raw.rolling,
+            canary: raw.canary,
+            diagnostics: raw.diagnostics,
+            successful_jobs_retention: raw.successful_jobs_retention,
+            job_logs_dir: raw.job_logs_dir,
+            jobs,
+            time_zone: raw.time_zone.unwrap_or(Tz::UTC),
+            history: raw.history,
+            notifications: raw.notifications,
+            metrics: raw.metrics,
         })
     }
 }
 
 impl Config {
+    fn build_stop_method(
+        stop_via: Option<RawStopVia>,
+        stop_command: Option<Vec<String>>,
+        default_stop_via: RawStopVia,
+        chart_name: &str,
+    ) -> Result<StopMethod, ConfigParseError> {
+        if stop_command.as_ref().is_some_and(|c| c.is_empty()) {
+            return Err(ConfigParseError::StopCommandEmpty {
+                chart_name: chart_name.to_string(),
+            });
+        }
+
+        let stop_via = match (stop_via, &stop_command) {
+            (Some(via), _) => via,
+            (None, Some(_)) => RawStopVia::Rcon,
+            (None, None) => default_stop_via,
+        };
+
+        match (stop_via, stop_command) {
+            (RawStopVia::Exec, Some(command)) => Ok(StopMethod::Exec(command)),
+            (RawStopVia::Exec, None) => Ok(StopMethod::Exec(vec![
+                "rcon-cli".to_string(),
+                "stop".to_string(),
+            ])),
+            (RawStopVia::Rcon, command) => Ok(StopMethod::Rcon(
+                command.unwrap_or_else(|| vec!["stop".to_string()]),
+            )),
+            (RawStopVia::None, Some(_)) => Err(ConfigParseError::StopCommandWithStopViaNone {
+                chart_name: chart_name.to_string(),
+            }),
+            (RawStopVia::None, None) => Ok(StopMethod::None),
+        }
+    }
+
+    /// `default_pod` is the pod and container of the chart, used by the `exec` and `rcon` steps
+    /// of hooks which do not name theirs.
+    fn build_custom_job(
+        job: RawCustomJob,
+        job_name: &str,
+        scope: &TemplateScope,
+        default_pod: Option<(&str, &str)>,
+    ) -> Result<CustomJob, ConfigParseError> {
+        let pod_target =
+            |pod: Option<String>, container: Option<String>| match (pod, container, default_pod) {
+                (Some(pod), Some(container), _) => Ok((pod, container)),
+                (pod, container, Some((default_pod, default_container))) => Ok((
+                    pod.unwrap_or_else(|| default_pod.to_string()),
+                    container.unwrap_or_else(|| default_container.to_string()),
+                )),
+                _ => Err(ConfigParseError::JobPodMissing {
+                    chart_name: scope.chart_name.clone(),
+                    job_name: job_name.to_string(),
+                }),
+            };
+
+        // `manifest_file` and `manifest_configmap` have been moved into `manifest` by
+        // `RawConfig::resolve_manifests` unless another source is also set
+        let external = job.manifest_file.is_some() || job.manifest_configmap.is_some();
+        if job.when.days_of_month.iter().any(|d| !(1..=31).contains(d)) {
+            return Err(ConfigParseError::JobConditionDayOfMonthInvalid {
+                chart_name: scope.chart_name.clone(),
+                job_name: job_name.to_string(),
+            });
+        }
+        if job.when.every_n_days == Some(0) {
+            return Err(ConfigParseError::JobConditionEveryNDaysZero {
+                chart_name: scope.chart_name.clone(),
+                job_name: job_name.to_string(),
+            });
+        }
+
+        let kind = match (job.manifest, job.template, job.exec, job.rcon) {
+            (Some(manifest), None, None, None) if !external => JobKind::KubernetesJob {
+                manifest: Box::new(manifest),
+                templated: false,
+            },
+            (None, Some(template), None, None) if !external => JobKind::KubernetesJob {
+                manifest: Box::new(scope.instantiate(&template, job.variables, job_name)?),
+                templated: true,
+            },
+            (None, None, Some(step), None) if !external => {
+                if step.command.is_empty() {
+                    return Err(ConfigParseError::ExecJobCommandEmpty {
+                        chart_name: scope.chart_name.clone(),
+                        job_name: job_name.to_string(),
+                    });
+                }
+                let (pod, container) = pod_target(step.pod, step.container)?;
+                JobKind::Exec(ExecStep {
+                    pod,
+                    container,
+                    command: step.command,
+                })
+            }
+            (None, None, None, Some(step)) if !external => {
+                if step.commands.is_empty() {
+                    return Err(ConfigParseError::RconJobCommandsEmpty {
+                        chart_name: scope.chart_name.clone(),
+                        job_name: job_name.to_string(),
+                    });
+                }
+                let (pod, container) = pod_target(step.pod, step.container)?;
+                JobKind::Rcon(RconStep {
+                    pod,
+                    container,
+                    commands: step.commands,
+                })
+            }
+            (None, None, None, None) if !external => {
+                return Err(ConfigParseError::JobManifestMissing {
+                    chart_name: scope.chart_name.clone(),
+                    job_name: job_name.to_string(),
+                });
+            }
+            _ => {
+                return Err(ConfigParseError::JobManifestConflict {
+                    chart_name: scope.chart_name.clone(),
+                    job_name: job_name.to_string(),
+                });
+            }
+        };
+
+        Ok(CustomJob {
+            dependencies: job.dependencies,
+            kind,
+            required: job.required,
+            completion_polling: job.completion_polling,
+            retries: job.retries,
+            retry_backoff: job.retry_backoff,
+            retry_on: job.retry_on,
+            when: job.when,
+        })
+    }
+
     fn build_jobs_after_snapshot(
         raw_jobs: BTreeMap<String, RawCustomJob>,
-        chart_name: &str,
+        scope: &TemplateScope,
     ) -> Result<BTreeMap<String, CustomJob>, ConfigParseError> {
         raw_jobs
             .into_iter()
             .map(|(name, job)| {
                 if name.contains('/') {
                     return Err(ConfigParseError::JobNameIncludesSlash {
-                        chart_name: chart_name.to_string(),
+                        chart_name: scope.chart_name.clone(),
                         job_name: name,
                     });
                 }
-                Ok((
-                    name.clone(),
-                    CustomJob {
-                        dependencies: job.dependencies,
-                        manifest: job.manifest,
-                        required: job.required,
-                        completion_polling: job.completion_polling,
-                    },
-                ))
+                let job = Self::build_custom_job(job, &name, scope, None)?;
+                Ok((name, job))
+            })
+            .collect()
+    }
+
+    /// `statefulset_name` and `rcon_container` give the pod in which the `exec` and `rcon`
+    /// hooks run by default.
+    fn build_hooks(
+        raw_hooks: Vec<RawCustomJob>,
+        point: HookPoint,
+        scope: &TemplateScope,
+        statefulset_name: &str,
+        rcon_container: &str,
+    ) -> Result<Vec<CustomJob>, ConfigParseError> {
+        let pod_name = format!("{statefulset_name}-0");
+        raw_hooks
+            .into_iter()
+            .enumerate()
+            .map(|(index, hook)| {
+                Self::build_custom_job(
+                    hook,
+                    &point.hook_name(index),
+                    scope,
+                    Some((&pod_name, rcon_container)),
+                )
+            })
+            .collect()
+    }
+
+    fn build_global_jobs(
+        raw_jobs: BTreeMap<String, RawGlobalJob>,
+        mcservers: &BTreeMap<String, RawMinecraftChart>,
+        scope: &TemplateScope,
+    ) -> Result<BTreeMap<String, GlobalJob>, ConfigParseError> {
+        let mut references = BTreeMap::new();
+        for (name, raw_job) in raw_jobs.iter() {
+            if name.contains('/') {
+                return Err(ConfigParseError::GlobalJobNameIncludesSlash {
+                    job_name: name.clone(),
+                });
+            }
+
+            let job_references = raw_job
+                .job
+                .dependencies
+                .iter()
+                .map(|reference| {
+                    let parsed = JobRef::parse(reference).ok_or_else(|| {
+                        ConfigParseError::InvalidJobReference {
+                            job_name: name.clone(),
+                            reference: reference.clone(),
+                        }
+                    })?;
+                    let exists = match &parsed {
+                        JobRef::McserverJob { mcserver, job } => mcservers
+                            .get(mcserver)
+                            .is_some_and(|s| s.jobs_after_snapshot.contains_key(job)),
+                        JobRef::McserverDown(mcserver) => mcservers.contains_key(mcserver),
+                        JobRef::Job(job) => raw_jobs.contains_key(job),
+                        JobRef::Phase(_) => true,
+                    };
+                    if !exists {
+                        return Err(ConfigParseError::JobReferenceNotFound {
+                            job_name: name.clone(),
+                            reference: reference.clone(),
+                        });
+                    }
+                    Ok(parsed)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            references.insert(name.clone(), job_references);
+        }
+
+        for name in references.keys() {
+            Self::check_global_job_cycle(name, &references, &mut Vec::new())?;
+        }
+
+        raw_jobs
+            .into_iter()
+            .map(|(name, raw_job)| {
+                let blocks_relaunch = match raw_job.blocks_relaunch {
+                    Some(targets) => targets
+                        .into_iter()
+                        .map(|target| match RelaunchTarget::parse(&target) {
+                            Some(RelaunchTarget::Mcserver(m)) if !mcservers.contains_key(&m) => {
+                                Err(ConfigParseError::InvalidRelaunchTarget {
+                                    job_name: name.clone(),
+                                    target,
+                                })
+                            }
+                            Some(parsed) => Ok(parsed),
+                            None => Err(ConfigParseError::InvalidRelaunchTarget {
+                                job_name: name.clone(),
+                                target,
+                            }),
+                        })
+                        .collect::<Result<BTreeSet<_>, _>>()?,
+                    None => Self::charts_required_down(&name, &references, mcservers),
+                };
+                let job = GlobalJob {
+                    // Kept for the `blocks_relaunch` of the jobs sorted after this one
+                    references: references.get(&name).cloned().unwrap_or_default(),
+                    job: Self::build_custom_job(raw_job.job, &name, scope, None)?,
+                    blocks_relaunch,
+                };
+                Ok((name, job))
+            })
+            .collect()
+    }
+
+    /// Fails if `name` can be reached again by following its `job:` references
+    fn check_global_job_cycle(
+        name: &str,
+        references: &BTreeMap<String, Vec<JobRef>>,
+        path: &mut Vec<String>,
+    ) -> Result<(), ConfigParseError> {
+        if let Some(start) = path.iter().position(|n| n == name) {
+            let cycle = path[start..]
+                .iter()
+                .map(String::as_str)
+                .chain(iter::once(name))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(ConfigParseError::JobDependencyCycle { cycle });
+        }
+
+        path.push(name.to_string());
+        for reference in references.get(name).into_iter().flatten() {
+            if let JobRef::Job(dependency) = reference {
+                Self::check_global_job_cycle(dependency, references, path)?;
+            }
+        }
+        path.pop();
+        Ok(())
+    }
+
+    /// Charts which must stay shut down while the job runs, including through `job:` references
+    fn charts_required_down(
+        name: &str,
+        references: &BTreeMap<String, Vec<JobRef>>,
+        mcservers: &BTreeMap<String, RawMinecraftChart>,
+    ) -> BTreeSet<RelaunchTarget> {
+        references
+            .get(name)
+            .into_iter()
+            .flatten()
+            .flat_map(|reference| match reference {
+                JobRef::McserverJob { mcserver, .. } | JobRef::McserverDown(mcserver) => {
+                    BTreeSet::from([RelaunchTarget::Mcserver(mcserver.clone())])
+                }
+                JobRef::Job(dependency) => {
+                    Self::charts_required_down(dependency, references, mcservers)
+                }
+                JobRef::Phase(RoutinePhase::McproxyDown) => {
+                    BTreeSet::from([RelaunchTarget::Mcproxy])
+                }
+                JobRef::Phase(RoutinePhase::AllServersDown) => mcservers
+                    .keys()
+                    .map(|m| RelaunchTarget::Mcserver(m.clone()))
+                    .chain(iter::once(RelaunchTarget::Mcproxy))
+                    .collect(),
             })
             .collect()
     }
 }
+
+/// Job templates and the built-in variables available to the jobs of one chart
+struct TemplateScope<'a> {
+    templates: &'a BTreeMap<String, JobTemplate>,
+    chart_name: String,
+    variables: BTreeMap<String, String>,
+}
+
+impl<'a> TemplateScope<'a> {
+    /// Scope of a chart, with `chart_name` (the key in the config), `statefulset_name` and
+    /// `namespace`
+    fn chart(
+        templates: &'a BTreeMap<String, JobTemplate>,
+        namespace: &str,
+        chart_key: &str,
+        statefulset_name: &str,
+    ) -> Self {
+        TemplateScope {
+            templates,
+            chart_name: statefulset_name.to_string(),
+            variables: BTreeMap::from([
+                ("chart_name".to_string(), chart_key.to_string()),
+                ("statefulset_name".to_string(), statefulset_name.to_string()),
+                ("namespace".to_string(), namespace.to_string()),
+            ]),
+        }
+    }
+
+    /// Scope of the top-level jobs, with `namespace` only
+    fn global(templates: &'a BTreeMap<String, JobTemplate>, namespace: &str) -> Self {
+        TemplateScope {
+            templates,
+            chart_name: "global".to_string(),
+            variables: BTreeMap::from([("namespace".to_string(), namespace.to_string())]),
+        }
+    }
+
+    /// Builds the manifest of `job_name` from `template`; built-in variables take precedence
+    /// over `variables`.
+    fn instantiate(
+        &self,
+        template: &str,
+        mut variables: BTreeMap<String, String>,
+        job_name: &str,
+    ) -> Result<Job, ConfigParseError> {
+        let mut manifest = self
+            .templates
+            .get(template)
+            .ok_or_else(|| ConfigParseError::JobTemplateNotFound {
+                chart_name: self.chart_name.clone(),
+                job_name: job_name.to_string(),
+                template: template.to_string(),
+            })?
+            .manifest
+            .clone();
+
+        variables.extend(self.variables.clone());
+        substitute_value(&mut manifest, &variables, is_runtime_variable).map_err(|error| {
+            ConfigParseError::JobTemplateSubstitution {
+                chart_name: self.chart_name.clone(),
+                job_name: job_name.to_string(),
+                error,
+            }
+        })?;
+
+        serde_yaml::from_value(manifest).map_err(|error| ConfigParseError::JobTemplateInvalid {
+            chart_name: self.chart_name.clone(),
+            job_name: job_name.to_string(),
+            error,
+        })
+    }
+}
@@ -0,0 +1,63 @@
+use serde::Deserialize;
+
+/// Configuration of the rolling restart routine, which keeps the mcproxy online
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub(crate) struct RollingConfig {
+    /// Server registered in the mcproxy to which players are moved before a restart
+    pub(crate) fallback_server: String,
+
+    /// Number of mcservers restarted at the same time
+    #[serde(default = "default_wave_size")]
+    pub(crate) wave_size: usize,
+
+    /// Proxy command sent through `rcon-cli` to move players off a server
+    ///
+    /// `{server}` and `{fallback}` are replaced with the mcserver key and `fallback_server`.
+    #[serde(default = "default_move_players_command")]
+    pub(crate) move_players_command: Vec<String>,
+}
+
+impl RollingConfig {
+    /// Argv executed in the mcproxy's RCON container to move players off `server`
+    pub(crate) fn move_players_argv(&self, server: &str) -> Vec<String> {
+        std::iter::once("rcon-cli".to_string())
+            .chain(self.move_players_command.iter().map(|arg| {
+                arg.replace("{server}", server)
+                    .replace("{fallback}", &self.fallback_server)
+            }))
+            .collect()
+    }
+}
+
+const fn default_wave_size() -> usize {
+    1
+}
+
+fn default_move_players_command() -> Vec<String> {
+    vec![
+        "send".to_string(),
+        "{server}".to_string(),
+        "{fallback}".to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_config_deserialize_defaults() {
+        let yaml_data = r#"
+          fallback_server: limbo
+        "#;
+
+        let config: RollingConfig = serde_yaml::from_str(yaml_data).unwrap();
+
+        assert_eq!(config.wave_size, 1);
+        assert_eq!(
+            config.move_players_argv("survival"),
+            vec!["rcon-cli", "send", "survival", "limbo"]
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::batch::v1::Job;
+use serde::Deserialize;
+use serde_yaml::Value;
+use thiserror::Error;
+
+/// Variables only known when the Job is created, left in place while the config is parsed
+pub(crate) const RUNTIME_VARIABLES: [&str; 3] = ["run_id", "date", "snapshot_name"];
+
+/// Job manifest shared by several charts
+///
+/// `{{name}}` placeholders in string values are replaced with the variable `name`.
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub(crate) struct JobTemplate {
+    /// Kubernetes Job YAML with placeholders
+    pub(crate) manifest: Value,
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum SubstitutionError {
+    #[error("unknown variable '{0}'")]
+    UnknownVariable(String),
+
+    #[error("unterminated placeholder in '{0}'")]
+    Unterminated(String),
+}
+
+/// Replaces `{{name}}` placeholders in `input` with `variables`.
+///
+/// Unknown placeholders for which `keep` returns true are left as they are; any other unknown
+/// name is an error.
+pub(crate) fn substitute_str(
+    input: &str,
+    variables: &BTreeMap<String, String>,
+    keep: fn(&str) -> bool,
+) -> Result<String, SubstitutionError> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| SubstitutionError::Unterminated(input.to_string()))?;
+        let name = after[..end].trim();
+        match variables.get(name) {
+            Some(value) => output.push_str(value),
+            None if keep(name) => output.push_str(&rest[start..start + end + 4]),
+            None => return Err(SubstitutionError::UnknownVariable(name.to_string())),
+        }
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Replaces placeholders in every string value of `value`, as [`substitute_str`] does.
+pub(crate) fn substitute_value(
+    value: &mut Value,
+    variables: &BTreeMap<String, String>,
+    keep: fn(&str) -> bool,
+) -> Result<(), SubstitutionError> {
+    match value {
+        Value::String(s) => *s = substitute_str(s, variables, keep)?,
+        Value::Sequence(sequence) => {
+            for item in sequence {
+                substitute_value(item, variables, keep)?;
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (_, item) in mapping.iter_mut() {
+                substitute_value(item, variables, keep)?;
+            }
+        }
+        Value::Tagged(tagged) => substitute_value(&mut tagged.value, variables, keep)?,
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+    Ok(())
+}
+
+/// Whether an unknown placeholder is left for [`substitute_job`] while the config is parsed
+pub(crate) fn is_runtime_variable(name: &str) -> bool {
+    RUNTIME_VARIABLES.contains(&name)
+}
+
+/// Replaces the [`RUNTIME_VARIABLES`] left in a Job manifest built from a template.
+pub(crate) fn substitute_job(job: &Job, variables: &BTreeMap<String, String>) -> Job {
+    let mut value = serde_yaml::to_value(job).expect("Job can be serialized");
+    substitute_value(&mut value, variables, |_| true)
+        .expect("placeholders of a template have been validated while parsing the config");
+    serde_yaml::from_value(value).expect("replacing strings keeps the Job valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitute_str() {
+        let variables = BTreeMap::from([("chart_name".to_string(), "lobby".to_string())]);
+
+        assert_eq!(
+            substitute_str("data-{{ chart_name }}-0", &variables, is_runtime_variable),
+            Ok("data-lobby-0".to_string())
+        );
+        assert_eq!(
+            substitute_str("{{chart_name}}-{{run_id}}", &variables, is_runtime_variable),
+            Ok("lobby-{{run_id}}".to_string())
+        );
+        assert_eq!(
+            substitute_str("{{pvc}}", &variables, is_runtime_variable),
+            Err(SubstitutionError::UnknownVariable("pvc".to_string()))
+        );
+        assert_eq!(
+            substitute_str("{{chart_name", &variables, is_runtime_variable),
+            Err(SubstitutionError::Unterminated("{{chart_name".to_string()))
+        );
+    }
+}
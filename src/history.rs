@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use chrono_tz::Tz;
+use k8s_openapi::api::core::v1::ConfigMap;
+use k8s_openapi::chrono::{DateTime, Utc};
+use kube::{Api, Client};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::instrument;
+
+use crate::config::history::HistoryConfig;
+use crate::error::{SpannedErr, SpannedExt};
+use crate::kubernetes_objects::configmap::apply_configmap_data;
+use crate::routine::daily::report::{RunReport, TaskStatus};
+
+/// Key of the history in its ConfigMap
+pub(crate) const HISTORY_CONFIGMAP_KEY: &str = "history.json";
+
+#[derive(Error, Debug)]
+pub enum HistoryError {
+    #[error("Kubernetes client error: {0}")]
+    KubeClient(#[from] SpannedErr<kube::Error>),
+
+    #[error("History in ConfigMap '{0}' is not valid: {1}")]
+    Invalid(String, serde_json::Error),
+
+    #[error("History cannot be serialized: {0}")]
+    Serialize(serde_json::Error),
+}
+
+/// Summary of a run kept in the history
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct RunSummary {
+    pub(crate) run_id: String,
+    pub(crate) routine: String,
+    pub(crate) started_at: DateTime<Utc>,
+    pub(crate) finished_at: DateTime<Utc>,
+    pub(crate) succeeded: bool,
+
+    /// Tasks which failed or were cancelled, and jobs which failed without being required
+    pub(crate) failed_tasks: Vec<String>,
+
+    /// Tasks of jobs skipped by their `when` condition
+    #[serde(default)]
+    pub(crate) skipped_tasks: Vec<String>,
+
+    pub(crate) servers: Vec<ServerSummary>,
+}
+
+/// Outcome of the tasks of a single mcserver within a run
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct ServerSummary {
+    /// Name of the mcserver in the config
+    pub(crate) name: String,
+    pub(crate) status: ServerStatus,
+    pub(crate) failed_tasks: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ServerStatus {
+    Succeeded,
+    Failed,
+
+    /// Some of the tasks were not run because of a failure elsewhere
+    Incomplete,
+}
+
+impl RunSummary {
+    pub(crate) fn from_report(report: &RunReport) -> RunSummary {
+        // Non-required job failures are reported as errors of `job/<chart>/<job>`, where
+        // `<chart>` is the internal chart name known from the downtime of the server
+        let servers_by_chart: BTreeMap<&str, &str> = report
+            .downtime
+            .iter()
+            .map(|d| (d.chart_name.as_str(), d.server.as_str()))
+            .collect();
+        let job_failures = report.errors.iter().filter_map(|e| {
+            let (chart, _) = e.source.strip_prefix("job/")?.split_once('/')?;
+            Some((servers_by_chart.get(chart).copied(), e.source.clone()))
+        });
+
+        let mut failed_tasks = Vec::new();
+        let mut servers: BTreeMap<String, ServerSummary> = BTreeMap::new();
+        let tasks = report.tasks.iter().map(|task| {
+            let server = task_server(&task.name);
+            (server, task.name.clone(), Some(task.status))
+        });
+        let job_failures = job_failures.map(|(server, source)| (server, source, None));
+        for (server, name, status) in tasks.chain(job_failures) {
+            let failed = matches!(
+                status,
+                None | Some(TaskStatus::Failed) | Some(TaskStatus::Cancelled)
+            );
+            if failed {
+                failed_tasks.push(name.clone());
+            }
+            let Some(server) = server else {
+                continue;
+            };
+            let summary = servers
+                .entry(server.to_string())
+                .or_insert_with(|| ServerSummary {
+                    name: server.to_string(),
+                    status: ServerStatus::Succeeded,
+                    failed_tasks: Vec::new(),
+                });
+            if failed {
+                summary.status = ServerStatus::Failed;
+                summary.failed_tasks.push(name);
+            } else if status == Some(TaskStatus::NotStarted)
+                && summary.status == ServerStatus::Succeeded
+            {
+                summary.status = ServerStatus::Incomplete;
+            }
+        }
+
+        RunSummary {
+            run_id: report.run_id.clone(),
+            routine: report.routine.clone(),
+            started_at: report.started_at,
+            finished_at: report.finished_at,
+            succeeded: report.succeeded,
+            failed_tasks,
+            skipped_tasks: report
+                .tasks
+                .iter()
+                .filter(|task| task.status == TaskStatus::Skipped)
+                .map(|task| task.name.clone())
+                .collect(),
+            servers: servers.into_values().collect(),
+        }
+    }
+}
+
+/// mcserver which a task such as `shutdown_mcserver/<name>` or
+/// `execute_job/after_snapshot/<name>/<job>` belongs to
+fn task_server(task_name: &str) -> Option<&str> {
+    let mut parts = task_name.split('/');
+    match (parts.next()?, parts.next()?, parts.next()) {
+        ("execute_job", "after_snapshot", Some(server)) => Some(server),
+        (phase, server, None) if phase.ends_with("_mcserver") || phase == "move_players" => {
+            Some(server)
+        }
+        _ => None,
+    }
+}
+
+/// Runs in the history, oldest first
+#[instrument("load_history", skip(client, config))]
+pub(crate) async fn load_history(
+    client: Client,
+    namespace: &str,
+    config: &HistoryConfig,
+) -> Result<Vec<RunSummary>, HistoryError> {
+    let api: Api<ConfigMap> = Api::namespaced(client, namespace);
+    let Some(configmap) = api.get_opt(&config.configmap).await.with_span_trace()? else {
+        return Ok(Vec::new());
+    };
+    let Some(json) = configmap
+        .data
+        .as_ref()
+        .and_then(|data| data.get(HISTORY_CONFIGMAP_KEY))
+    else {
+        return Ok(Vec::new());
+    };
+    serde_json::from_str(json).map_err(|e| HistoryError::Invalid(config.configmap.clone(), e))
+}
+
+/// Adds the run to the history, dropping the oldest runs beyond `max_runs`.
+#[instrument("append_history", skip(client, config, summary), fields(run_id = %summary.run_id))]
+pub(crate) async fn append_history(
+    client: Client,
+    namespace: &str,
+    config: &HistoryConfig,
+    summary: RunSummary,
+) -> Result<(), HistoryError> {
+    let mut runs = load_history(client.clone(), namespace, config).await?;
+    runs.push(summary);
+    let excess = runs.len().saturating_sub(config.max_runs);
+    runs.drain(..excess);
+
+    let json = serde_json::to_string(&runs).map_err(HistoryError::Serialize)?;
+    apply_configmap_data(
+        client,
+        namespace,
+        &config.configmap,
+        HISTORY_CONFIGMAP_KEY,
+        json,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Renders the runs as a table, newest first.
+///
+/// With `server`, only the runs which touched the mcserver are listed, with its own status and
+/// failed tasks.
+pub(crate) fn format_history(runs: &[RunSummary], server: Option<&str>, time_zone: Tz) -> String {
+    let mut output = format!(
+        "{:<16}  {:<8}  {:<20}  {:<10}  {:>9}  FAILED TASKS\n",
+        "RUN ID", "ROUTINE", "STARTED AT", "STATUS", "DURATION"
+    );
+    for run in runs.iter().rev() {
+        let (status, failed_tasks) = match server {
+            None => (
+                if run.succeeded { "succeeded" } else { "failed" },
+                &run.failed_tasks,
+            ),
+            Some(server) => {
+                let Some(summary) = run.servers.iter().find(|s| s.name == server) else {
+                    continue;
+                };
+                let status = match summary.status {
+                    ServerStatus::Succeeded => "succeeded",
+                    ServerStatus::Failed => "failed",
+                    ServerStatus::Incomplete => "incomplete",
+                };
+                (status, &summary.failed_tasks)
+            }
+        };
+        let seconds = (run.finished_at - run.started_at).num_seconds();
+        let _ = writeln!(
+            output,
+            "{:<16}  {:<8}  {:<20}  {:<10}  {:>9}  {}",
+            run.run_id,
+            run.routine,
+            run.started_at
+                .with_timezone(&time_zone)
+                .format("%Y-%m-%d %a %H:%M"),
+            status,
+            format!("{}m{:02}s", seconds / 60, seconds % 60),
+            if failed_tasks.is_empty() {
+                "-".to_string()
+            } else {
+                failed_tasks.join(", ")
+            }
+        );
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routine::daily::report::{DowntimeReport, ErrorReport, TaskReport};
+
+    #[test]
+    fn test_summary_from_report() {
+        let at = |seconds: i64| DateTime::from_timestamp(1_800_000_000 + seconds, 0).unwrap();
+        let task = |name: &str, status: TaskStatus| TaskReport {
+            name: name.to_string(),
+            status,
+            started_at: None,
+            finished_at: None,
+            duration_seconds: None,
+        };
+        let report = RunReport {
+            run_id: "20270115-040000".to_string(),
+            routine: "daily".to_string(),
+            started_at: at(0),
+            finished_at: at(754),
+            succeeded: false,
+            tasks: vec![
+                task("argocd_teardown", TaskStatus::Succeeded),
+                task("shutdown_mcserver/lobby", TaskStatus::Succeeded),
+                task("relaunch_mcserver/lobby", TaskStatus::Succeeded),
+                task(
+                    "execute_job/after_snapshot/lobby/render-map",
+                    TaskStatus::Skipped,
+                ),
+                task("shutdown_mcserver/survival", TaskStatus::Succeeded),
+                task(
+                    "execute_job/after_snapshot/survival/backup",
+                    TaskStatus::Failed,
+                ),
+                task("relaunch_mcserver/survival", TaskStatus::NotStarted),
+                task("relaunch_mcserver/creative", TaskStatus::NotStarted),
+            ],
+            errors: vec![ErrorReport::new(
+                "job/mcserver-lobby/render-map",
+                "failed",
+                None,
+            )],
+            argocd_applications: vec![],
+            jobs: vec![],
+            downtime: vec![DowntimeReport {
+                server: "lobby".to_string(),
+                chart_name: "mcserver-lobby".to_string(),
+                down_at: at(10),
+                up_at: Some(at(100)),
+                downtime_seconds: 90.0,
+            }],
+        };
+
+        let summary = RunSummary::from_report(&report);
+        assert_eq!(
+            summary.failed_tasks,
+            vec![
+                "execute_job/after_snapshot/survival/backup",
+                "job/mcserver-lobby/render-map"
+            ]
+        );
+        assert_eq!(
+            summary.skipped_tasks,
+            vec!["execute_job/after_snapshot/lobby/render-map"]
+        );
+        let statuses: Vec<(&str, ServerStatus)> = summary
+            .servers
+            .iter()
+            .map(|s| (s.name.as_str(), s.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("creative", ServerStatus::Incomplete),
+                ("lobby", ServerStatus::Failed),
+                ("survival", ServerStatus::Failed),
+            ]
+        );
+
+        let table = format_history(&[summary], Some("survival"), Tz::UTC);
+        let row = table.lines().nth(1).unwrap();
+        assert!(row.starts_with("20270115-040000   daily     2027-01-15 Fri 08:00"));
+        assert!(row.contains("failed"));
+        assert!(row.contains("12m34s"));
+        assert!(row.ends_with("execute_job/after_snapshot/survival/backup"));
+        assert_eq!(format_history(&[], None, Tz::UTC).lines().count(), 1);
+    }
+}
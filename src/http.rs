@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Method, Request, Uri};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use thiserror::Error;
+use tracing::warn;
+
+pub(crate) type HttpClient = Client<HttpsConnector<HttpConnector>, Full<Bytes>>;
+
+#[derive(Error, Debug)]
+pub enum HttpError {
+    #[error("Request cannot be built: {0}")]
+    Request(#[from] hyper::http::Error),
+
+    #[error("Request failed: {0}")]
+    Client(#[from] hyper_util::client::legacy::Error),
+
+    #[error("Response cannot be read: {0}")]
+    Body(#[from] hyper::Error),
+
+    #[error("Server responded with {status}: {body}")]
+    Status { status: u16, body: String },
+}
+
+/// HTTP(S) client trusting the system's root certificates
+pub(crate) fn http_client() -> HttpClient {
+    Client::builder(TokioExecutor::new()).build(https_connector())
+}
+
+/// Connector for both http and https URLs, trusting the system's root certificates
+pub(crate) fn https_connector() -> HttpsConnector<HttpConnector> {
+    let mut roots = rustls::RootCertStore::empty();
+    let native = rustls_native_certs::load_native_certs();
+    for error in native.errors {
+        warn!("Failed to load a system root certificate: {}", error);
+    }
+    roots.add_parsable_certificates(native.certs);
+
+    let tls = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .expect("ring supports the default protocol versions")
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    HttpsConnectorBuilder::new()
+        .with_tls_config(tls)
+        .https_or_http()
+        .enable_http1()
+        .build()
+}
+
+/// Whether `url` is an absolute http or https URL
+pub(crate) fn is_http_url(url: &str) -> bool {
+    url.parse::<Uri>()
+        .is_ok_and(|uri| matches!(uri.scheme_str(), Some("http" | "https")) && uri.host().is_some())
+}
+
+/// Sends `body` and fails unless the response status is 2xx.
+pub(crate) async fn send(
+    http: &HttpClient,
+    method: Method,
+    url: &Uri,
+    headers: &BTreeMap<String, String>,
+    content_type: &str,
+    body: Vec<u8>,
+) -> Result<(), HttpError> {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(url.clone())
+        .header(CONTENT_TYPE, content_type);
+    for (name, value) in headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    let request = request.body(Full::new(Bytes::from(body)))?;
+
+    let response = http.request(request).await?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.into_body().collect().await?.to_bytes();
+    Err(HttpError::Status {
+        status: status.as_u16(),
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+/// Gets `url` and returns the body of the response, failing unless its status is 2xx.
+pub(crate) async fn fetch(http: &HttpClient, url: &Uri) -> Result<String, HttpError> {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(url.clone())
+        .body(Full::new(Bytes::new()))?;
+
+    let response = http.request(request).await?;
+    let status = response.status();
+    let body = response.into_body().collect().await?.to_bytes();
+    let body = String::from_utf8_lossy(&body).into_owned();
+    if status.is_success() {
+        return Ok(body);
+    }
+    Err(HttpError::Status {
+        status: status.as_u16(),
+        body,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// Request received by [`stand_in`]
+    pub(crate) struct ReceivedRequest {
+        pub(crate) head: String,
+        pub(crate) body: String,
+    }
+
+    impl ReceivedRequest {
+        pub(crate) fn json(&self) -> serde_json::Value {
+            serde_json::from_str(&self.body).unwrap()
+        }
+    }
+
+    /// Local HTTP server answering every request with `status`
+    pub(crate) async fn stand_in(
+        status: u16,
+    ) -> (String, mpsc::UnboundedReceiver<ReceivedRequest>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let (head, body) = loop {
+                    let mut chunk = [0; 4096];
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buf).into_owned();
+                    let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length: usize = head
+                        .lines()
+                        .find_map(|l| {
+                            l.to_lowercase()
+                                .strip_prefix("content-length: ")?
+                                .parse()
+                                .ok()
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break (head.to_string(), body.to_string());
+                    }
+                };
+                let response = format!(
+                    "HTTP/1.1 {status} Stand-in\r\nconnection: close\r\ncontent-length: 2\r\n\r\nok"
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                let _ = tx.send(ReceivedRequest { head, body });
+            }
+        });
+        (url, rx)
+    }
+}
@@ -10,10 +10,11 @@ use kube::{Api, Client};
 use serde_json::json;
 use tokio::sync::RwLock;
 use tracing::field::Empty;
-use tracing::{Instrument, Level, Span, error, info, trace_span, warn};
+use tracing::{Instrument, Level, Span, info, trace_span, warn};
 use tracing_error::ExtractSpanTrace;
 
 use crate::kubernetes_objects::{ARGOCD_NAMESPACE, MANAGEER_ROLE_NAME};
+use crate::logging::log_error;
 
 #[derive(Dbg)]
 pub(super) struct Teardown {
@@ -23,6 +24,10 @@ pub(super) struct Teardown {
     #[dbg(skip)]
     client: Client,
     counter: AtomicUsize,
+
+    /// Whether automated sync stays disabled once the last guard is closed, set when any guard
+    /// is closed by [`TearingArgoCdGuard::close_without_automated_sync`]
+    without_automated_sync: bool,
 }
 
 #[derive(Debug)]
@@ -64,13 +69,12 @@ impl TearingArgoCd for SharedArgoCd {
                 if let Some(upstream) = upstream
                     && let Err(e) = upstream.close().await
                 {
-                    error!(
-                        "Failed to rollback upstream ArgoCd teardown after failing to sync_teardown: {}",
-                        e
+                    log_error(
+                        &format!(
+                            "Failed to rollback upstream ArgoCd teardown after failing to sync_teardown: {e}"
+                        ),
+                        e.span_trace(),
                     );
-                    if let Some(span_trace) = e.span_trace() {
-                        eprintln!("\n{}", color_spantrace::colorize(span_trace));
-                    }
                 }
                 return Err(e);
             }
@@ -84,6 +88,7 @@ impl TearingArgoCd for SharedArgoCd {
             original_sync_policy,
             client: client.clone(),
             counter: AtomicUsize::new(0),
+            without_automated_sync: false,
         }));
 
         write_guard.tear = Some(Ok(tear.clone()));
@@ -93,14 +98,27 @@ impl TearingArgoCd for SharedArgoCd {
 }
 
 impl TearingArgoCdGuard {
+    pub(crate) async fn close(self) -> Result<(), ArgoCdError> {
+        self.close_with(true).await
+    }
+
+    /// Same as [`close`](Self::close), but the Application and its parents get back their
+    /// original sync policy without automated sync.
+    pub(crate) async fn close_without_automated_sync(self) -> Result<(), ArgoCdError> {
+        self.close_with(false).await
+    }
+
     #[tracing::instrument(
         "tearing_argocd_guard/close",
         level = Level::TRACE,
         skip(self),
         fields(counter = Empty)
     )]
-    pub(crate) async fn close(mut self) -> Result<(), ArgoCdError> {
+    async fn close_with(mut self, automated_sync: bool) -> Result<(), ArgoCdError> {
         self.dropped = true;
+        if !automated_sync {
+            self.tear.write().await.without_automated_sync = true;
+        }
         let c = self
             .tear
             .read()
@@ -113,20 +131,31 @@ impl TearingArgoCdGuard {
             let argocd = &teardown.argocd.upgrade().ok_or_else(ArgoCdError::dropped)?;
             let write_guard = &mut argocd.write().await;
 
-            sync_tearup(
-                &write_guard.name,
-                teardown.client.clone(),
-                teardown.original_sync_policy.clone(),
-            )
-            .await?;
+            let automated_sync = !teardown.without_automated_sync;
+            let sync_policy =
+                teardown
+                    .original_sync_policy
+                    .clone()
+                    .map(|policy| ApplicationSyncPolicy {
+                        automated: policy.automated.filter(|_| automated_sync),
+                        ..policy
+                    });
+            sync_tearup(&write_guard.name, teardown.client.clone(), sync_policy).await?;
 
-            info!(
-                "ArgoCD application '{}' was successfully restored.",
-                write_guard.name
-            );
+            if automated_sync {
+                info!(
+                    "ArgoCD application '{}' was successfully restored.",
+                    write_guard.name
+                );
+            } else {
+                warn!(
+                    "ArgoCD application '{}' was restored without automated sync.",
+                    write_guard.name
+                );
+            }
 
             if let Some(upstream) = teardown.upstream.take() {
-                Box::pin(upstream.close()).await?;
+                Box::pin(upstream.close_with(automated_sync)).await?;
             }
             write_guard.tear = None;
         }
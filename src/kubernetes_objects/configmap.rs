@@ -0,0 +1,41 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::ConfigMap;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Patch, PatchParams};
+use kube::{Api, Client};
+use tracing::instrument;
+
+use crate::error::{SpannedErr, SpannedExt};
+
+use super::{MANAGED_BY_LABEL, MANAGEER_ROLE_NAME};
+
+/// Creates the ConfigMap or replaces `key` in it with server-side apply.
+#[instrument("apply_configmap_data", skip(client, value))]
+pub(crate) async fn apply_configmap_data(
+    client: Client,
+    namespace: &str,
+    name: &str,
+    key: &str,
+    value: String,
+) -> Result<ConfigMap, SpannedErr<kube::Error>> {
+    let api: Api<ConfigMap> = Api::namespaced(client, namespace);
+    let configmap = ConfigMap {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            namespace: Some(namespace.to_string()),
+            labels: Some(BTreeMap::from([(
+                MANAGED_BY_LABEL.to_string(),
+                MANAGEER_ROLE_NAME.to_string(),
+            )])),
+            ..Default::default()
+        },
+        data: Some(BTreeMap::from([(key.to_string(), value)])),
+        ..Default::default()
+    };
+
+    let params = PatchParams::apply(MANAGEER_ROLE_NAME).force();
+    api.patch(name, &params, &Patch::Apply(&configmap))
+        .await
+        .with_span_trace()
+}
@@ -1,5 +1,10 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
 use k8s_openapi::api::batch::v1::Job;
+use serde::Deserialize;
 
+use crate::config::condition::JobCondition;
 use crate::config::polling::PollingConfig;
 
 #[derive(Debug, Clone)]
@@ -7,12 +12,262 @@ pub(crate) struct CustomJob {
     /// Names of jobs that must complete before this job starts
     pub(crate) dependencies: Vec<String>,
 
-    /// Kubernetes Job YAML
-    pub(crate) manifest: Job,
+    /// What the job runs
+    pub(crate) kind: JobKind,
 
     /// Whether the job's successful completion is required to continue routine or not
     pub(crate) required: bool,
 
     /// Polling configuration for waiting for job completion
+    ///
+    /// Only `max_wait` is used by `exec` and `rcon` jobs, as the timeout of each command.
     pub(crate) completion_polling: PollingConfig,
+
+    /// Number of times the job is recreated after a failed attempt
+    pub(crate) retries: u32,
+
+    /// Wait before the first retry, doubled on every further retry
+    pub(crate) retry_backoff: Duration,
+
+    /// Kinds of failures which are retried
+    pub(crate) retry_on: Vec<RetryOn>,
+
+    /// Calendar condition on which the job runs
+    pub(crate) when: JobCondition,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum JobKind {
+    /// `kubernetes_job`: a Kubernetes Job created from a manifest
+    KubernetesJob {
+        /// Kubernetes Job YAML
+        manifest: Box<Job>,
+
+        /// Whether the manifest was built from a template and may contain runtime variables
+        templated: bool,
+    },
+
+    /// `exec`: a command executed in a running pod
+    Exec(ExecStep),
+
+    /// `rcon`: RCON commands sent through `rcon-cli` in a running pod
+    Rcon(RconStep),
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(test, derive(PartialEq))]
+pub(crate) struct ExecStep {
+    /// Name of the pod in which the command is executed, by default the chart's pod for hooks
+    ///
+    /// The pod must be running while the job runs: the routine does not wait for a pod it
+    /// shuts down in parallel, such as another mcserver, to come back.
+    pub(crate) pod: String,
+
+    /// Container of the pod in which the command is executed, by default `rcon_container` of
+    /// the chart for hooks
+    pub(crate) container: String,
+
+    /// Command and its arguments; the job fails if it exits with a non-zero status
+    pub(crate) command: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(test, derive(PartialEq))]
+pub(crate) struct RconStep {
+    /// Name of the pod in which `rcon-cli` is executed
+    ///
+    /// The pod must be running while the job runs, as for [`ExecStep::pod`].
+    pub(crate) pod: String,
+
+    /// Container of the pod which has `rcon-cli`
+    pub(crate) container: String,
+
+    /// Commands sent in order; the job stops at the first failing one
+    pub(crate) commands: Vec<RconCommand>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[cfg_attr(test, derive(PartialEq))]
+pub(crate) struct RconCommand {
+    /// RCON command such as `save-all flush`
+    pub(crate) command: String,
+
+    /// Text which must appear in the response for the command to succeed
+    pub(crate) expect_output: Option<String>,
+}
+
+impl RconCommand {
+    /// `rcon-cli` invocation sending the command
+    pub(crate) fn argv(&self) -> Vec<String> {
+        vec!["rcon-cli".to_string(), self.command.clone()]
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RetryOn {
+    /// The Job or command has failed
+    Failure,
+
+    /// The Job or command did not finish within `completion_polling.max_wait`
+    Timeout,
+}
+
+impl CustomJob {
+    /// Wait before the given retry attempt, starting from 1
+    pub(crate) fn backoff_for_attempt(&self, attempt: u32) -> Duration {
+        self.retry_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+    }
+}
+
+/// Job defined at the top level of the config, not tied to a single chart
+#[derive(Debug, Clone)]
+pub(crate) struct GlobalJob {
+    /// The job itself; `job.dependencies` holds the references as written in the config
+    pub(crate) job: CustomJob,
+
+    /// Parsed `job.dependencies`
+    pub(crate) references: Vec<JobRef>,
+
+    /// Charts whose relaunch waits for this job to finish
+    pub(crate) blocks_relaunch: BTreeSet<RelaunchTarget>,
+}
+
+/// Dependency of a top-level job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum JobRef {
+    /// `mcserver:<mcserver>/<job>`: a job in `jobs_after_snapshot` of an mcserver
+    McserverJob { mcserver: String, job: String },
+
+    /// `mcserver:<mcserver>`: the mcserver has been shut down
+    McserverDown(String),
+
+    /// `job:<name>`: another top-level job
+    Job(String),
+
+    /// `phase:<phase>`: a phase of the routine
+    Phase(RoutinePhase),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RoutinePhase {
+    /// `phase:mcproxy_down`: the mcproxy has been shut down
+    McproxyDown,
+
+    /// `phase:all_servers_down`: the mcproxy and every mcserver have been shut down
+    AllServersDown,
+}
+
+/// Chart whose relaunch can be blocked by a top-level job
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum RelaunchTarget {
+    /// `mcserver:<mcserver>`
+    Mcserver(String),
+
+    /// `mcproxy`
+    Mcproxy,
+}
+
+impl JobRef {
+    /// Parses a reference such as `mcserver:lobby/backup` or `phase:all_servers_down`
+    pub(crate) fn parse(reference: &str) -> Option<JobRef> {
+        let (kind, target) = reference.split_once(':')?;
+        match kind {
+            "mcserver" => match target.split_once('/') {
+                Some((mcserver, job)) if !mcserver.is_empty() && !job.is_empty() => {
+                    Some(JobRef::McserverJob {
+                        mcserver: mcserver.to_string(),
+                        job: job.to_string(),
+                    })
+                }
+                Some(_) => None,
+                None if !target.is_empty() => Some(JobRef::McserverDown(target.to_string())),
+                None => None,
+            },
+            "job" if !target.is_empty() && !target.contains('/') => {
+                Some(JobRef::Job(target.to_string()))
+            }
+            "phase" => match target {
+                "mcproxy_down" => Some(JobRef::Phase(RoutinePhase::McproxyDown)),
+                "all_servers_down" => Some(JobRef::Phase(RoutinePhase::AllServersDown)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl RelaunchTarget {
+    /// Parses `mcserver:<mcserver>` or `mcproxy`
+    pub(crate) fn parse(target: &str) -> Option<RelaunchTarget> {
+        match target.split_once(':') {
+            None if target == "mcproxy" => Some(RelaunchTarget::Mcproxy),
+            Some(("mcserver", name)) if !name.is_empty() && !name.contains('/') => {
+                Some(RelaunchTarget::Mcserver(name.to_string()))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_for_attempt() {
+        let job = CustomJob {
+            dependencies: vec![],
+            kind: JobKind::KubernetesJob {
+                manifest: Box::default(),
+                templated: false,
+            },
+            required: true,
+            completion_polling: PollingConfig::default(),
+            retries: 3,
+            retry_backoff: Duration::from_secs(30),
+            retry_on: vec![RetryOn::Failure],
+            when: JobCondition::default(),
+        };
+        assert_eq!(job.backoff_for_attempt(1), Duration::from_secs(30));
+        assert_eq!(job.backoff_for_attempt(2), Duration::from_secs(60));
+        assert_eq!(job.backoff_for_attempt(3), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn test_job_ref_parse() {
+        assert_eq!(
+            JobRef::parse("mcserver:lobby/backup"),
+            Some(JobRef::McserverJob {
+                mcserver: "lobby".to_string(),
+                job: "backup".to_string()
+            })
+        );
+        assert_eq!(
+            JobRef::parse("mcserver:lobby"),
+            Some(JobRef::McserverDown("lobby".to_string()))
+        );
+        assert_eq!(
+            JobRef::parse("job:db-dump"),
+            Some(JobRef::Job("db-dump".to_string()))
+        );
+        assert_eq!(
+            JobRef::parse("phase:all_servers_down"),
+            Some(JobRef::Phase(RoutinePhase::AllServersDown))
+        );
+        assert_eq!(JobRef::parse("backup"), None);
+        assert_eq!(JobRef::parse("mcserver:lobby/"), None);
+        assert_eq!(JobRef::parse("phase:all_servers_up"), None);
+
+        assert_eq!(
+            RelaunchTarget::parse("mcproxy"),
+            Some(RelaunchTarget::Mcproxy)
+        );
+        assert_eq!(
+            RelaunchTarget::parse("mcserver:lobby"),
+            Some(RelaunchTarget::Mcserver("lobby".to_string()))
+        );
+        assert_eq!(RelaunchTarget::parse("lobby"), None);
+    }
 }
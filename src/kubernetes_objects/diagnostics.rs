@@ -0,0 +1,258 @@
+use std::fmt::Display;
+
+use k8s_openapi::api::core::v1::{Event, Pod};
+use kube::api::{ListParams, LogParams};
+use kube::{Api, Client};
+use tracing::{instrument, warn};
+
+use crate::config::diagnostics::DiagnosticsConfig;
+
+use super::event::{event_last_seen, list_object_events};
+
+/// Logs, events and status collected from the objects involved in a failure
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    pub objects: Vec<ObjectDiagnostics>,
+    pub pods: Vec<PodDiagnostics>,
+}
+
+/// Recent events of a non-pod object such as a StatefulSet or a Job
+#[derive(Debug, Clone)]
+pub struct ObjectDiagnostics {
+    pub kind: String,
+    pub name: String,
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PodDiagnostics {
+    pub pod_name: String,
+    /// Pod status rendered as YAML
+    pub status: String,
+    pub events: Vec<String>,
+    pub containers: Vec<ContainerLogs>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContainerLogs {
+    pub container_name: String,
+    pub logs: Option<String>,
+    /// Logs of the previous instance of the container, if it has restarted
+    pub previous_logs: Option<String>,
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for object in &self.objects {
+            writeln!(f, "=== {} '{}' events:", object.kind, object.name)?;
+            for event in &object.events {
+                writeln!(f, "  {event}")?;
+            }
+        }
+        for pod in &self.pods {
+            writeln!(f, "=== Pod '{}' status:", pod.pod_name)?;
+            for line in pod.status.lines() {
+                writeln!(f, "  {line}")?;
+            }
+            writeln!(f, "=== Pod '{}' events:", pod.pod_name)?;
+            for event in &pod.events {
+                writeln!(f, "  {event}")?;
+            }
+            for container in &pod.containers {
+                if let Some(previous_logs) = &container.previous_logs {
+                    writeln!(
+                        f,
+                        "=== Pod '{}' container '{}' previous logs:",
+                        pod.pod_name, container.container_name
+                    )?;
+                    writeln!(f, "{previous_logs}")?;
+                }
+                if let Some(logs) = &container.logs {
+                    writeln!(
+                        f,
+                        "=== Pod '{}' container '{}' logs:",
+                        pod.pod_name, container.container_name
+                    )?;
+                    writeln!(f, "{logs}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Collects diagnostics of a StatefulSet and its pods.
+#[instrument("collect_statefulset_diagnostics", skip(client, config))]
+pub(crate) async fn collect_statefulset_diagnostics(
+    client: Client,
+    namespace: &str,
+    statefulset_name: &str,
+    config: &DiagnosticsConfig,
+) -> Diagnostics {
+    // charts/minecraft-v2 runs a single replica per StatefulSet
+    let pod_name = format!("{statefulset_name}-0");
+    let object = collect_object_diagnostics(
+        client.clone(),
+        namespace,
+        "StatefulSet",
+        statefulset_name,
+        config,
+    )
+    .await;
+    let pod = Api::<Pod>::namespaced(client.clone(), namespace)
+        .get_opt(&pod_name)
+        .await
+        .inspect_err(|e| warn!("Failed to get pod '{pod_name}' for diagnostics: {e}"))
+        .ok()
+        .flatten();
+
+    Diagnostics {
+        objects: vec![object],
+        pods: match pod {
+            Some(pod) => vec![collect_pod_diagnostics(client, namespace, &pod, config).await],
+            None => vec![],
+        },
+    }
+}
+
+/// Collects diagnostics of a Job and its pods.
+#[instrument("collect_job_diagnostics", skip(client, config))]
+pub(crate) async fn collect_job_diagnostics(
+    client: Client,
+    namespace: &str,
+    job_name: &str,
+    config: &DiagnosticsConfig,
+) -> Diagnostics {
+    let object =
+        collect_object_diagnostics(client.clone(), namespace, "Job", job_name, config).await;
+    let pods = Api::<Pod>::namespaced(client.clone(), namespace)
+        .list(&ListParams::default().labels(&format!("job-name={job_name}")))
+        .await
+        .inspect_err(|e| warn!("Failed to list pods of job '{job_name}' for diagnostics: {e}"))
+        .map(|list| list.items)
+        .unwrap_or_default();
+
+    let mut pod_diagnostics = Vec::new();
+    for pod in pods.iter() {
+        pod_diagnostics.push(collect_pod_diagnostics(client.clone(), namespace, pod, config).await);
+    }
+
+    Diagnostics {
+        objects: vec![object],
+        pods: pod_diagnostics,
+    }
+}
+
+async fn collect_object_diagnostics(
+    client: Client,
+    namespace: &str,
+    kind: &str,
+    name: &str,
+    config: &DiagnosticsConfig,
+) -> ObjectDiagnostics {
+    ObjectDiagnostics {
+        kind: kind.to_string(),
+        name: name.to_string(),
+        events: collect_events(client, namespace, kind, name, config).await,
+    }
+}
+
+async fn collect_pod_diagnostics(
+    client: Client,
+    namespace: &str,
+    pod: &Pod,
+    config: &DiagnosticsConfig,
+) -> PodDiagnostics {
+    let pod_name = pod.metadata.name.clone().unwrap_or_default();
+    let pod_api: Api<Pod> = Api::namespaced(client.clone(), namespace);
+
+    let container_statuses: Vec<(String, bool)> = pod
+        .status
+        .iter()
+        .flat_map(|s| {
+            s.init_container_statuses
+                .iter()
+                .flatten()
+                .chain(s.container_statuses.iter().flatten())
+        })
+        .map(|c| (c.name.clone(), c.restart_count > 0))
+        .collect();
+
+    let mut containers = Vec::new();
+    for (container_name, restarted) in container_statuses {
+        let logs = fetch_logs(&pod_api, &pod_name, &container_name, false, config).await;
+        let previous_logs = if restarted {
+            fetch_logs(&pod_api, &pod_name, &container_name, true, config).await
+        } else {
+            None
+        };
+        containers.push(ContainerLogs {
+            container_name,
+            logs,
+            previous_logs,
+        });
+    }
+
+    PodDiagnostics {
+        status: pod
+            .status
+            .as_ref()
+            .and_then(|s| serde_yaml::to_string(s).ok())
+            .unwrap_or_default(),
+        events: collect_events(client, namespace, "Pod", &pod_name, config).await,
+        containers,
+        pod_name,
+    }
+}
+
+async fn fetch_logs(
+    pod_api: &Api<Pod>,
+    pod_name: &str,
+    container_name: &str,
+    previous: bool,
+    config: &DiagnosticsConfig,
+) -> Option<String> {
+    let params = LogParams {
+        container: Some(container_name.to_string()),
+        previous,
+        tail_lines: Some(config.tail_lines),
+        ..Default::default()
+    };
+    pod_api
+        .logs(pod_name, &params)
+        .await
+        .inspect_err(|e| {
+            warn!(
+                "Failed to get logs of container '{container_name}' in pod '{pod_name}' for diagnostics: {e}"
+            )
+        })
+        .ok()
+}
+
+async fn collect_events(
+    client: Client,
+    namespace: &str,
+    kind: &str,
+    name: &str,
+    config: &DiagnosticsConfig,
+) -> Vec<String> {
+    let mut events = list_object_events(client, namespace, kind, name)
+        .await
+        .inspect_err(|e| warn!("Failed to list events of {kind} '{name}' for diagnostics: {e}"))
+        .unwrap_or_default();
+    events.sort_by_key(event_last_seen);
+    let skip = events.len().saturating_sub(config.max_events);
+    events.iter().skip(skip).map(format_event).collect()
+}
+
+fn format_event(event: &Event) -> String {
+    format!(
+        "{} {} {}: {}",
+        event_last_seen(event)
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_else(|| "<unknown>".to_string()),
+        event.type_.as_deref().unwrap_or("<unknown>"),
+        event.reason.as_deref().unwrap_or("<unknown>"),
+        event.message.as_deref().unwrap_or("")
+    )
+}
@@ -0,0 +1,92 @@
+use k8s_openapi::api::core::v1::Event;
+use k8s_openapi::chrono::{DateTime, Utc};
+use kube::api::ListParams;
+use kube::{Api, Client};
+
+use super::pod::PodFailure;
+
+/// Warning event reasons which will not resolve without intervention
+const FATAL_EVENT_REASONS: [&str; 2] = ["FailedScheduling", "FailedCreate"];
+
+/// Lists events of the given object.
+pub(crate) async fn list_object_events(
+    client: Client,
+    namespace: &str,
+    kind: &str,
+    name: &str,
+) -> Result<Vec<Event>, kube::Error> {
+    let event_api: Api<Event> = Api::namespaced(client, namespace);
+    let params = ListParams::default().fields(&format!(
+        "involvedObject.kind={kind},involvedObject.name={name}"
+    ));
+    Ok(event_api.list(&params).await?.items)
+}
+
+/// Time at which the event was last observed
+pub(crate) fn event_last_seen(event: &Event) -> Option<DateTime<Utc>> {
+    event
+        .series
+        .as_ref()
+        .and_then(|s| s.last_observed_time.as_ref().map(|t| t.0))
+        .or_else(|| event.last_timestamp.as_ref().map(|t| t.0))
+        .or_else(|| event.event_time.as_ref().map(|t| t.0))
+        .or_else(|| event.metadata.creation_timestamp.as_ref().map(|t| t.0))
+}
+
+/// Finds the latest fatal warning event observed after `since`.
+pub(crate) fn fatal_event(events: &[Event], since: DateTime<Utc>) -> Option<PodFailure> {
+    events
+        .iter()
+        .filter(|e| e.type_.as_deref() == Some("Warning"))
+        .filter(|e| {
+            e.reason
+                .as_deref()
+                .is_some_and(|r| FATAL_EVENT_REASONS.contains(&r))
+        })
+        .filter_map(|e| event_last_seen(e).filter(|t| *t >= since).map(|t| (t, e)))
+        .max_by_key(|(t, _)| *t)
+        .map(|(_, e)| PodFailure {
+            object_name: e.involved_object.name.clone().unwrap_or_default(),
+            reason: e.reason.clone().unwrap_or_default(),
+            message: e.message.clone().unwrap_or_default(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::core::v1::ObjectReference;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+    use k8s_openapi::chrono::TimeDelta;
+
+    fn event(reason: &str, type_: &str, last_timestamp: DateTime<Utc>) -> Event {
+        Event {
+            involved_object: ObjectReference {
+                name: Some("mcserver-lobby-0".to_string()),
+                ..Default::default()
+            },
+            reason: Some(reason.to_string()),
+            type_: Some(type_.to_string()),
+            message: Some(format!("{reason} message")),
+            last_timestamp: Some(Time(last_timestamp)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fatal_event() {
+        let now = Utc::now();
+        let events = vec![
+            event("FailedScheduling", "Warning", now - TimeDelta::hours(1)),
+            event("Pulled", "Normal", now),
+            event("FailedMount", "Warning", now),
+        ];
+        assert!(fatal_event(&events, now - TimeDelta::minutes(1)).is_none());
+
+        let events = [events, vec![event("FailedScheduling", "Warning", now)]].concat();
+        let failure = fatal_event(&events, now - TimeDelta::minutes(1)).unwrap();
+        assert_eq!(failure.object_name, "mcserver-lobby-0");
+        assert_eq!(failure.reason, "FailedScheduling");
+        assert_eq!(failure.message, "FailedScheduling message");
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt::{Debug, Display};
+
+use k8s_openapi::NamespaceResourceScope;
+use k8s_openapi::api::core::v1::ObjectReference;
+use kube::runtime::events::{Event, EventType, Recorder, Reporter};
+use kube::{Api, Client, Resource};
+use serde::de::DeserializeOwned;
+use tracing::warn;
+
+use super::MANAGEER_ROLE_NAME;
+
+/// Maximum size of the note of an event accepted by the API server
+const MAX_NOTE_BYTES: usize = 1024;
+
+/// Step of the routine reported as an event on the affected object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RoutineAction {
+    /// Automated sync of the ArgoCD Application disabled
+    Teardown,
+    ScaleDown,
+
+    /// Server stopped and its pod gone
+    Stop,
+    Relaunch,
+
+    /// Automated sync of the ArgoCD Application enabled again
+    Restore,
+
+    /// Job created by the routine finished
+    RunJob,
+}
+
+impl RoutineAction {
+    fn action(self) -> &'static str {
+        match self {
+            RoutineAction::Teardown => "Teardown",
+            RoutineAction::ScaleDown => "ScaleDown",
+            RoutineAction::Stop => "Stop",
+            RoutineAction::Relaunch => "Relaunch",
+            RoutineAction::Restore => "Restore",
+            RoutineAction::RunJob => "RunJob",
+        }
+    }
+
+    fn success_reason(self) -> &'static str {
+        match self {
+            RoutineAction::Teardown => "TornDown",
+            RoutineAction::ScaleDown => "ScaledDown",
+            RoutineAction::Stop => "Stopped",
+            RoutineAction::Relaunch => "Relaunched",
+            RoutineAction::Restore => "Restored",
+            RoutineAction::RunJob => "Succeeded",
+        }
+    }
+
+    /// Event of the step, a `Normal` one on success and a `Warning` one otherwise
+    fn event<E: Display>(self, result: Result<String, E>) -> Event {
+        let (type_, reason, note) = match result {
+            Ok(note) => (EventType::Normal, self.success_reason().to_string(), note),
+            Err(e) => (
+                EventType::Warning,
+                format!("{}Failed", self.action()),
+                e.to_string(),
+            ),
+        };
+        Event {
+            type_,
+            reason,
+            note: Some(truncate(note, MAX_NOTE_BYTES)),
+            action: self.action().to_string(),
+            secondary: None,
+        }
+    }
+}
+
+/// Publishes events on the objects touched by the routine, so that whoever inspects them sees
+/// that man10routine is responsible.
+#[derive(Clone)]
+pub(crate) struct EventRecorder {
+    client: Client,
+    recorder: Recorder,
+}
+
+impl EventRecorder {
+    pub(crate) fn new(client: Client) -> EventRecorder {
+        let reporter = Reporter {
+            controller: MANAGEER_ROLE_NAME.to_string(),
+            instance: std::env::var("HOSTNAME").ok(),
+        };
+        EventRecorder {
+            recorder: Recorder::new(client.clone(), reporter),
+            client,
+        }
+    }
+
+    /// Publishes the event on the object `namespace/name` of kind `K`.
+    ///
+    /// Failures are only logged since events are informative.
+    pub(crate) async fn publish<K, E>(
+        &self,
+        namespace: &str,
+        name: &str,
+        action: RoutineAction,
+        result: Result<String, E>,
+    ) where
+        K: Resource<DynamicType = (), Scope = NamespaceResourceScope>
+            + Clone
+            + DeserializeOwned
+            + Debug,
+        E: Display,
+    {
+        // `kubectl describe` only lists the events referencing the object's UID
+        let api: Api<K> = Api::namespaced(self.client.clone(), namespace);
+        let reference = match api.get_opt(name).await {
+            Ok(Some(object)) => object.object_ref(&()),
+            _ => ObjectReference {
+                api_version: Some(K::api_version(&()).to_string()),
+                kind: Some(K::kind(&()).to_string()),
+                name: Some(name.to_string()),
+                namespace: Some(namespace.to_string()),
+                ..Default::default()
+            },
+        };
+        self.publish_on(&reference, action, result).await;
+    }
+
+    /// Publishes the event on the referenced object, logging failures.
+    pub(crate) async fn publish_on<E: Display>(
+        &self,
+        reference: &ObjectReference,
+        action: RoutineAction,
+        result: Result<String, E>,
+    ) {
+        let event = action.event(result);
+        if let Err(e) = self.recorder.publish(&event, reference).await {
+            warn!(
+                "Failed to record event '{}' on {} '{}': {}",
+                event.reason,
+                reference.kind.as_deref().unwrap_or_default(),
+                reference.name.as_deref().unwrap_or_default(),
+                e
+            );
+        }
+    }
+}
+
+fn truncate(mut text: String, max_bytes: usize) -> String {
+    if text.len() > max_bytes {
+        let mut end = max_bytes - '…'.len_utf8();
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        text.push('…');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routine_event() {
+        let event = RoutineAction::Relaunch.event::<String>(Ok("Scaled to 1 replica".to_string()));
+        assert_eq!(event.type_, EventType::Normal);
+        assert_eq!(event.reason, "Relaunched");
+        assert_eq!(event.action, "Relaunch");
+
+        let event = RoutineAction::Restore.event(Err("ツ".repeat(400)));
+        assert_eq!(event.type_, EventType::Warning);
+        assert_eq!(event.reason, "RestoreFailed");
+        let note = event.note.unwrap();
+        assert!(note.len() <= MAX_NOTE_BYTES);
+        assert!(note.ends_with('…'));
+    }
+}
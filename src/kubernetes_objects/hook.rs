@@ -0,0 +1,42 @@
+use super::custom_job::CustomJob;
+
+/// Point of the routine at which the hooks of a chart are run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HookPoint {
+    /// While the server is still up, before it is stopped
+    BeforeShutdown,
+
+    /// Once the server has been relaunched and is ready
+    AfterRelaunch,
+}
+
+impl HookPoint {
+    /// Prefix of the hook names, also used in the names of the created Jobs
+    pub(crate) fn prefix(&self) -> &'static str {
+        match self {
+            HookPoint::BeforeShutdown => "before-shutdown",
+            HookPoint::AfterRelaunch => "after-relaunch",
+        }
+    }
+
+    /// Name of the hook at `index` in the list, used in config errors, logs and Job names
+    pub(crate) fn hook_name(&self, index: usize) -> String {
+        format!("{}-{}", self.prefix(), index + 1)
+    }
+}
+
+/// Hooks of a chart, custom jobs run in order at each point
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ChartHooks {
+    pub(crate) before_shutdown: Vec<CustomJob>,
+    pub(crate) after_relaunch: Vec<CustomJob>,
+}
+
+impl ChartHooks {
+    pub(crate) fn get(&self, point: HookPoint) -> &[CustomJob] {
+        match point {
+            HookPoint::BeforeShutdown => &self.before_shutdown,
+            HookPoint::AfterRelaunch => &self.after_relaunch,
+        }
+    }
+}
@@ -1,7 +1,11 @@
 use k8s_openapi::api::batch::v1::{Job, JobStatus};
+use k8s_openapi::chrono::Utc;
 use kube::Api;
 use kube::Client;
+use kube::api::{DeleteParams, ListParams};
+use std::time::Duration;
 use thiserror::Error;
+use tokio::time::Instant;
 use tracing::error;
 use tracing::warn;
 use tracing::{info, instrument};
@@ -9,6 +13,10 @@ use tracing::{info, instrument};
 use crate::config::polling::PollingConfig;
 use crate::error::SpannedErr;
 use crate::error::SpannedExt;
+use crate::kubernetes_objects::watch::ObjectWatcher;
+use crate::kubernetes_objects::{
+    ATTEMPT_LABEL, CHART_LABEL, JOB_LABEL, MANAGED_BY_LABEL, MANAGEER_ROLE_NAME, RUN_ID_LABEL,
+};
 
 #[derive(Error, Debug)]
 pub enum WaitJobFinishedError {
@@ -22,60 +30,195 @@ pub enum WaitJobFinishedError {
     JobCompletionCheckTimeout(u64),
 }
 
+#[derive(Error, Debug)]
+pub enum GarbageCollectJobsError {
+    #[error("Jobs cannot be listed: {0}")]
+    List(#[from] kube::Error),
+
+    #[error("Failed to delete {} expired jobs ({} deleted): {}", failed.len(), deleted, failed.join(", "))]
+    Delete { deleted: usize, failed: Vec<String> },
+}
+
+/// Final outcome of a Job decided by its conditions
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum JobOutcome {
+    Succeeded,
+    Failed(JobFailure),
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{reason}: {message}")]
+pub struct JobFailure {
+    pub reason: String,
+    pub message: String,
+}
+
+/// Decides the outcome of a Job from its `Complete`/`SuccessCriteriaMet` and
+/// `Failed`/`FailureTarget` conditions.
+///
+/// Returns `None` while the Job is pending, suspended, running or retrying failed pods.
+pub(crate) fn job_outcome(status: &JobStatus) -> Option<JobOutcome> {
+    let conditions = status
+        .conditions
+        .iter()
+        .flatten()
+        .filter(|c| c.status == "True");
+    for condition in conditions {
+        match condition.type_.as_str() {
+            "Complete" | "SuccessCriteriaMet" => return Some(JobOutcome::Succeeded),
+            "Failed" | "FailureTarget" => {
+                return Some(JobOutcome::Failed(JobFailure {
+                    reason: condition
+                        .reason
+                        .clone()
+                        .unwrap_or_else(|| condition.type_.clone()),
+                    message: condition.message.clone().unwrap_or_default(),
+                }));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Maximum length of a Job name, which is also used as the `job-name` label value of its pods
+const MAX_JOB_NAME_LENGTH: usize = 63;
+
+/// Returns a copy of the manifest to be created for the given attempt of this run.
+///
+/// A fixed `metadata.name` gets the run id and retry attempt as suffix so that every attempt
+/// creates a new Job, and the Job and its pods are labeled with the run id, chart, job name and
+/// attempt.
+pub(crate) fn prepare_job_manifest(
+    manifest: &Job,
+    run_id: &str,
+    chart_name: &str,
+    job_name: &str,
+    attempt: u32,
+) -> Job {
+    let mut job = manifest.clone();
+
+    if let Some(name) = job.metadata.name.take() {
+        let suffix = match attempt {
+            0 => run_id.to_string(),
+            _ => format!("{run_id}-attempt-{attempt}"),
+        };
+        let max_base_length = MAX_JOB_NAME_LENGTH - suffix.len() - 1;
+        let base = name[..name.len().min(max_base_length)].trim_end_matches('-');
+        job.metadata.name = Some(format!("{base}-{suffix}"));
+    } else if job.metadata.generate_name.is_none() {
+        job.metadata.generate_name = Some(format!("{chart_name}-{job_name}-"));
+    }
+
+    let attempt = attempt.to_string();
+    let labels = [
+        (MANAGED_BY_LABEL, MANAGEER_ROLE_NAME),
+        (RUN_ID_LABEL, run_id),
+        (CHART_LABEL, chart_name),
+        (JOB_LABEL, job_name),
+        (ATTEMPT_LABEL, attempt.as_str()),
+    ];
+    let job_labels = job.metadata.labels.get_or_insert_default();
+    let pod_labels = job
+        .spec
+        .get_or_insert_default()
+        .template
+        .metadata
+        .get_or_insert_default()
+        .labels
+        .get_or_insert_default();
+    for (key, value) in labels {
+        job_labels.insert(key.to_string(), value.to_string());
+        pod_labels.insert(key.to_string(), value.to_string());
+    }
+
+    job
+}
+
+/// Deletes the Job together with its pods.
+#[instrument("delete_job", skip(client))]
+pub(crate) async fn delete_job(
+    client: Client,
+    namespace: &str,
+    job_name: &str,
+) -> Result<(), kube::Error> {
+    let job_api: Api<Job> = Api::namespaced(client, namespace);
+    job_api
+        .delete(job_name, &DeleteParams::background())
+        .await?;
+    info!("Job '{}' and its pods have been deleted.", job_name);
+    Ok(())
+}
+
+/// Deletes successful Jobs created by the routine which completed more than `retention` ago.
+///
+/// A Job which cannot be deleted does not stop the others from being deleted.
+#[instrument("garbage_collect_jobs", skip(client))]
+pub(crate) async fn garbage_collect_jobs(
+    client: Client,
+    namespace: &str,
+    retention: Duration,
+) -> Result<usize, GarbageCollectJobsError> {
+    let job_api: Api<Job> = Api::namespaced(client.clone(), namespace);
+    let threshold = Utc::now() - retention;
+    let jobs = job_api
+        .list(&ListParams::default().labels(&format!("{MANAGED_BY_LABEL}={MANAGEER_ROLE_NAME}")))
+        .await?;
+
+    let mut deleted = 0;
+    let mut failed = Vec::new();
+    for job in jobs.items {
+        let Some(status) = job.status.as_ref() else {
+            continue;
+        };
+        let expired = status
+            .completion_time
+            .as_ref()
+            .is_some_and(|t| t.0 < threshold);
+        if expired && job_outcome(status) == Some(JobOutcome::Succeeded) {
+            let name = job.metadata.name.unwrap_or_default();
+            match delete_job(client.clone(), namespace, &name).await {
+                Ok(()) => deleted += 1,
+                Err(e) => {
+                    warn!("Failed to delete expired job '{}': {}", name, e);
+                    failed.push(name);
+                }
+            }
+        }
+    }
+    if !failed.is_empty() {
+        return Err(GarbageCollectJobsError::Delete { deleted, failed });
+    }
+    Ok(deleted)
+}
+
 #[instrument("wait_until_job_finished", skip(client), level = "trace")]
 pub(crate) async fn wait_until_job_finished(
     client: Client,
     namespace: &str,
     job_name: &str,
     polling_config: &PollingConfig,
-) -> Result<JobStatus, SpannedErr<WaitJobFinishedError>> {
+) -> Result<JobOutcome, SpannedErr<WaitJobFinishedError>> {
     info!(
-        "Waiting {} to {} seconds for job '{}' to finish...",
-        polling_config.initial_wait.as_secs(),
+        "Waiting up to {} seconds for job '{}' to finish...",
         polling_config.max_wait.as_secs(),
         job_name
     );
-    tokio::time::sleep(polling_config.initial_wait).await;
-    let mut wait_duration = polling_config.initial_wait;
+    let started_at = Instant::now();
     let mut errors_count = 0u64;
     let job_api: Api<Job> = Api::namespaced(client, namespace);
+    let mut watcher = ObjectWatcher::new(job_api, job_name, polling_config);
+    let mut last_status: Option<JobStatus> = None;
     loop {
-        match job_api.get(job_name).await {
-            Ok(Job {
+        match watcher.next().await {
+            Ok(Some(Job {
                 status: Some(status),
                 ..
-            }) => {
-                if status.active.unwrap_or(0) == 0 {
-                    info!(
-                        "Job '{}' has finished after {} seconds.",
-                        job_name,
-                        wait_duration.as_secs()
-                    );
-                    break Ok(status);
-                }
-
-                info!(
-                    "Job '{}' still running after {} seconds (active: {:?}). Waiting another {} seconds...",
-                    job_name,
-                    wait_duration.as_secs(),
-                    status.active,
-                    polling_config.poll_interval.as_secs()
-                );
-                if wait_duration >= polling_config.max_wait {
-                    error!(
-                        "Waited more than {} seconds for job '{}' to finish.",
-                        wait_duration.as_secs(),
-                        job_name
-                    );
-                    break Err(WaitJobFinishedError::JobCompletionCheckTimeout(
-                        wait_duration.as_secs(),
-                    ))
-                    .with_span_trace();
-                }
-                wait_duration += polling_config.poll_interval;
-                tokio::time::sleep(polling_config.poll_interval).await;
+            })) => {
+                last_status = Some(status);
             }
-            Ok(_) => break Err(WaitJobFinishedError::JobHasNoStatus).with_span_trace(),
+            Ok(Some(_)) => break Err(WaitJobFinishedError::JobHasNoStatus).with_span_trace(),
+            Ok(None) => {}
             Err(e) => {
                 warn!("Error while checking job '{}': {}", job_name, e);
                 warn!(
@@ -90,9 +233,132 @@ pub(crate) async fn wait_until_job_finished(
                     );
                     break Err(WaitJobFinishedError::KubeClient(e)).with_span_trace();
                 }
-                wait_duration += polling_config.error_wait;
                 tokio::time::sleep(polling_config.error_wait).await;
+                continue;
+            }
+        }
+
+        let wait_duration = started_at.elapsed();
+        if let Some(status) = &last_status {
+            if let Some(outcome) = job_outcome(status) {
+                info!(
+                    "Job '{}' has finished after {} seconds.",
+                    job_name,
+                    wait_duration.as_secs()
+                );
+                break Ok(outcome);
             }
+
+            info!(
+                "Job '{}' still running after {} seconds (active: {:?}, failed: {:?}).",
+                job_name,
+                wait_duration.as_secs(),
+                status.active,
+                status.failed,
+            );
+        }
+        if wait_duration >= polling_config.max_wait {
+            error!(
+                "Waited more than {} seconds for job '{}' to finish.",
+                wait_duration.as_secs(),
+                job_name
+            );
+            break Err(WaitJobFinishedError::JobCompletionCheckTimeout(
+                wait_duration.as_secs(),
+            ))
+            .with_span_trace();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::batch::v1::JobCondition;
+
+    fn status(conditions: &[(&str, &str)]) -> JobStatus {
+        JobStatus {
+            conditions: Some(
+                conditions
+                    .iter()
+                    .map(|(type_, status)| JobCondition {
+                        type_: type_.to_string(),
+                        status: status.to_string(),
+                        reason: Some(format!("{type_}Reason")),
+                        message: Some(format!("{type_} message")),
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_prepare_job_manifest() {
+        let manifest: Job = serde_yaml::from_str(
+            r#"
+metadata:
+  name: backup-survival-world-with-a-very-long-name-exceeding-limit
+spec:
+  template:
+    spec:
+      containers: []
+"#,
+        )
+        .unwrap();
+
+        let job = prepare_job_manifest(&manifest, "20261018-040000", "survival", "backup", 0);
+        let name = job.metadata.name.as_deref().unwrap();
+        assert!(name.len() <= MAX_JOB_NAME_LENGTH);
+        assert!(name.ends_with("-20261018-040000"));
+        assert!(!name.contains("--"));
+
+        let labels = job.metadata.labels.as_ref().unwrap();
+        assert_eq!(labels[RUN_ID_LABEL], "20261018-040000");
+        assert_eq!(labels[CHART_LABEL], "survival");
+        assert_eq!(labels[JOB_LABEL], "backup");
+        assert_eq!(
+            job.spec.unwrap().template.metadata.unwrap().labels.unwrap()[MANAGED_BY_LABEL],
+            MANAGEER_ROLE_NAME
+        );
+
+        let retried = prepare_job_manifest(&manifest, "20261018-040000", "survival", "backup", 2);
+        let name = retried.metadata.name.as_deref().unwrap();
+        assert!(name.len() <= MAX_JOB_NAME_LENGTH);
+        assert!(name.ends_with("-20261018-040000-attempt-2"));
+        assert_eq!(retried.metadata.labels.unwrap()[ATTEMPT_LABEL], "2");
+
+        let generated =
+            prepare_job_manifest(&Job::default(), "20261018-040000", "survival", "backup", 0);
+        assert_eq!(generated.metadata.name, None);
+        assert_eq!(
+            generated.metadata.generate_name.as_deref(),
+            Some("survival-backup-")
+        );
+    }
+
+    #[test]
+    fn test_job_outcome() {
+        // Not started yet: no pods are active, but the Job has not finished
+        assert_eq!(job_outcome(&JobStatus::default()), None);
+        assert_eq!(job_outcome(&status(&[("Suspended", "True")])), None);
+        assert_eq!(job_outcome(&status(&[("Complete", "False")])), None);
+
+        assert_eq!(
+            job_outcome(&status(&[("SuccessCriteriaMet", "True")])),
+            Some(JobOutcome::Succeeded)
+        );
+        assert_eq!(
+            job_outcome(&status(&[("Complete", "True")])),
+            Some(JobOutcome::Succeeded)
+        );
+        assert_eq!(
+            job_outcome(&status(&[("FailureTarget", "True"), ("Failed", "True")])),
+            Some(JobOutcome::Failed(JobFailure {
+                reason: "FailureTargetReason".to_string(),
+                message: "FailureTarget message".to_string(),
+            }))
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use futures::{AsyncBufReadExt, StreamExt};
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::LogParams;
+use kube::runtime::{WatchStreamExt, watcher};
+use kube::{Api, Client};
+use tokio::fs::{File, OpenOptions, create_dir_all};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{Instrument, info, warn};
+
+/// A line of a container log
+struct LogLine {
+    pod_name: String,
+    container_name: String,
+    line: String,
+}
+
+/// Follows the logs of all pods of a Job in the background and emits each line as a tracing
+/// event in the span it was spawned from, optionally appending them to a file.
+pub(crate) struct JobLogFollower {
+    stop: Option<oneshot::Sender<()>>,
+    handle: JoinHandle<()>,
+}
+
+impl JobLogFollower {
+    pub(crate) fn spawn(
+        client: Client,
+        namespace: &str,
+        job_name: &str,
+        log_dir: Option<PathBuf>,
+    ) -> Self {
+        let (stop, stop_rx) = oneshot::channel();
+        let handle = tokio::spawn(
+            follow_job_logs(
+                client,
+                namespace.to_string(),
+                job_name.to_string(),
+                log_dir,
+                stop_rx,
+            )
+            .in_current_span(),
+        );
+        Self {
+            stop: Some(stop),
+            handle,
+        }
+    }
+
+    /// Stops following new pods and waits up to `grace` for the remaining logs to be emitted.
+    pub(crate) async fn finish(mut self, grace: Duration) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if tokio::time::timeout(grace, &mut self.handle).await.is_err() {
+            warn!("Logs of the job were not fully collected within {grace:?}.");
+        }
+    }
+}
+
+impl Drop for JobLogFollower {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn follow_job_logs(
+    client: Client,
+    namespace: String,
+    job_name: String,
+    log_dir: Option<PathBuf>,
+    mut stop: oneshot::Receiver<()>,
+) {
+    let mut log_file = match log_dir {
+        Some(dir) => open_log_file(dir, &job_name).await,
+        None => None,
+    };
+
+    let pod_api: Api<Pod> = Api::namespaced(client, &namespace);
+    let mut pods = watcher(
+        pod_api.clone(),
+        watcher::Config::default().labels(&format!("job-name={job_name}")),
+    )
+    .default_backoff()
+    .applied_objects()
+    .boxed();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<LogLine>();
+    // A container restarted in place is followed again, since its new instance has a new log
+    let mut followed: HashSet<(String, String, i32)> = HashSet::new();
+    let mut followers = JoinSet::new();
+    let mut tx = Some(tx);
+
+    loop {
+        tokio::select! {
+            _ = &mut stop, if tx.is_some() => {
+                // Followers end when their containers terminate
+                tx = None;
+            }
+            pod = pods.next(), if tx.is_some() => match pod {
+                Some(Ok(pod)) => {
+                    let pod_name = pod.metadata.name.clone().unwrap_or_default();
+                    for (container_name, restart_count) in started_containers(&pod) {
+                        let key = (pod_name.clone(), container_name.clone(), restart_count);
+                        if followed.insert(key) {
+                            followers.spawn(
+                                follow_container(
+                                    pod_api.clone(),
+                                    pod_name.clone(),
+                                    container_name,
+                                    tx.clone().expect("sender exists while following pods"),
+                                )
+                                .in_current_span(),
+                            );
+                        }
+                    }
+                }
+                Some(Err(e)) => warn!("Error while watching pods of job '{job_name}': {e}"),
+                None => tx = None,
+            },
+            line = rx.recv() => match line {
+                Some(line) => emit(&job_name, line, &mut log_file).await,
+                None => break,
+            },
+        }
+    }
+
+    followers.shutdown().await;
+    if let Some(file) = log_file.as_mut()
+        && let Err(e) = file.flush().await
+    {
+        warn!("Failed to flush log file of job '{job_name}': {e}");
+    }
+}
+
+async fn follow_container(
+    pod_api: Api<Pod>,
+    pod_name: String,
+    container_name: String,
+    tx: mpsc::UnboundedSender<LogLine>,
+) {
+    let params = LogParams {
+        container: Some(container_name.clone()),
+        follow: true,
+        ..Default::default()
+    };
+    let stream = match pod_api.log_stream(&pod_name, &params).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            warn!("Failed to follow logs of container '{container_name}' in pod '{pod_name}': {e}");
+            return;
+        }
+    };
+
+    let mut lines = stream.lines();
+    while let Some(line) = lines.next().await {
+        match line {
+            Ok(line) => {
+                let _ = tx.send(LogLine {
+                    pod_name: pod_name.clone(),
+                    container_name: container_name.clone(),
+                    line,
+                });
+            }
+            Err(e) => {
+                warn!("Log stream of container '{container_name}' in pod '{pod_name}' broke: {e}");
+                break;
+            }
+        }
+    }
+}
+
+async fn emit(job_name: &str, log_line: LogLine, log_file: &mut Option<File>) {
+    let LogLine {
+        pod_name,
+        container_name,
+        line,
+    } = log_line;
+    info!(
+        kubernetes_job_name = %job_name,
+        pod_name = %pod_name,
+        container = %container_name,
+        "{line}"
+    );
+    if let Some(file) = log_file {
+        let record = format!("[{pod_name}/{container_name}] {line}\n");
+        if let Err(e) = file.write_all(record.as_bytes()).await {
+            warn!("Failed to write log file of job '{job_name}': {e}. Disabling the log file.");
+            *log_file = None;
+        }
+    }
+}
+
+/// File of the logs of a job, whose name includes the run id and attempt
+fn log_file_path(dir: &Path, job_name: &str) -> PathBuf {
+    dir.join(format!("{job_name}.log"))
+}
+
+async fn open_log_file(dir: PathBuf, job_name: &str) -> Option<File> {
+    let path = log_file_path(&dir, job_name);
+    let file = async {
+        create_dir_all(&dir).await?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+    }
+    .await;
+    match file {
+        Ok(file) => {
+            info!("Saving logs of job '{job_name}' to {}", path.display());
+            Some(file)
+        }
+        Err(e) => {
+            warn!("Failed to open log file {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Names and restart counts of the containers of the pod which have started and therefore have
+/// logs
+fn started_containers(pod: &Pod) -> Vec<(String, i32)> {
+    pod.status
+        .iter()
+        .flat_map(|s| {
+            s.init_container_statuses
+                .iter()
+                .flatten()
+                .chain(s.container_statuses.iter().flatten())
+        })
+        .filter(|c| {
+            c.state
+                .as_ref()
+                .is_some_and(|s| s.running.is_some() || s.terminated.is_some())
+        })
+        .map(|c| (c.name.clone(), c.restart_count))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_started_containers() {
+        let pod: Pod = serde_json::from_value(serde_json::json!({
+            "metadata": { "name": "backup-20250101-000000-abcde" },
+            "status": {
+                "initContainerStatuses": [{
+                    "name": "fetch",
+                    "image": "busybox",
+                    "imageID": "",
+                    "ready": false,
+                    "restartCount": 0,
+                    "state": { "terminated": { "exitCode": 0 } }
+                }],
+                "containerStatuses": [
+                    {
+                        "name": "upload",
+                        "image": "restic",
+                        "imageID": "",
+                        "ready": true,
+                        "restartCount": 2,
+                        "state": { "running": {} }
+                    },
+                    {
+                        "name": "sidecar",
+                        "image": "busybox",
+                        "imageID": "",
+                        "ready": false,
+                        "restartCount": 0,
+                        "state": { "waiting": { "reason": "ContainerCreating" } }
+                    }
+                ]
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            started_containers(&pod),
+            vec![("fetch".to_string(), 0), ("upload".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn test_log_file_path() {
+        assert_eq!(
+            log_file_path(Path::new("/var/log/jobs"), "backup-lobby-20250101-000000-2"),
+            PathBuf::from("/var/log/jobs/backup-lobby-20250101-000000-2.log")
+        );
+    }
+}
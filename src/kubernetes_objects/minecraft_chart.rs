@@ -1,6 +1,7 @@
 use crate::kubernetes_objects::argocd::tearing::TearingArgoCd;
 use kube::Client;
 use std::collections::BTreeMap;
+use std::iter;
 use std::sync::{Arc, Weak};
 use thiserror::Error;
 use tokio::sync::RwLock;
@@ -10,6 +11,7 @@ use tracing_error::{ExtractSpanTrace, SpanTrace};
 use super::argocd::tearing::TearingArgoCdGuard;
 use super::argocd::{ArgoCdError, WeakArgoCd};
 use super::custom_job::CustomJob;
+use super::hook::ChartHooks;
 
 pub(crate) type SharedMinecraftChart = Arc<RwLock<MinecraftChart>>;
 pub(crate) type WeakMinecraftChart = Weak<RwLock<MinecraftChart>>;
@@ -31,9 +33,51 @@ pub(crate) struct MinecraftChart {
     /// Whether this MinecraftChart is required to restart the mcproxy
     pub(crate) required_to_start: bool,
 
+    /// How the server is stopped gracefully before the StatefulSet is scaled down
+    pub(crate) stop: StopMethod,
+
+    /// Hooks run before the shutdown and after the relaunch
+    pub(crate) hooks: ChartHooks,
+
     argocd_tear: Option<Result<TearingArgoCdGuard, ArgoCdError>>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum StopMethod {
+    /// Execute the argv directly in the RCON container
+    Exec(Vec<String>),
+
+    /// Send the command through `rcon-cli` in the RCON container
+    Rcon(Vec<String>),
+
+    /// Do not send any stop command
+    None,
+}
+
+impl StopMethod {
+    /// Argv to execute in the RCON container, if any
+    pub(crate) fn argv(&self) -> Option<Vec<String>> {
+        match self {
+            StopMethod::Exec(command) => Some(command.clone()),
+            StopMethod::Rcon(command) => Some(
+                iter::once("rcon-cli".to_string())
+                    .chain(command.iter().cloned())
+                    .collect(),
+            ),
+            StopMethod::None => None,
+        }
+    }
+
+    /// How the server is stopped, worded for events and logs
+    pub(crate) fn description(&self) -> String {
+        match self {
+            StopMethod::Exec(command) => format!("by executing `{}`", command.join(" ")),
+            StopMethod::Rcon(command) => format!("by sending `{}` over RCON", command.join(" ")),
+            StopMethod::None => "without a stop command".to_string(),
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum MinecraftChartError {
     #[error("ArgoCD error: {0}")]
@@ -55,6 +99,8 @@ impl MinecraftChart {
         rcon_container: String,
         jobs_after_snapshot: BTreeMap<String, CustomJob>,
         required_to_start: bool,
+        stop: StopMethod,
+        hooks: ChartHooks,
     ) -> SharedMinecraftChart {
         Arc::new(RwLock::new(MinecraftChart {
             name,
@@ -63,6 +109,8 @@ impl MinecraftChart {
             jobs_after_snapshot,
             argocd_tear: None,
             required_to_start,
+            stop,
+            hooks,
         }))
     }
 
@@ -97,17 +145,30 @@ impl MinecraftChart {
         }
     }
 
+    /// Name of the ArgoCD Application of the chart
+    pub(crate) async fn argocd_name(&self) -> String {
+        self.argocd
+            .upgrade()
+            .expect("ArgoCD object of a MinecraftChart has been dropped")
+            .read()
+            .await
+            .name
+            .clone()
+    }
+
+    /// Closes the ArgoCD teardown of the chart, enabling automated sync again unless
+    /// `automated_sync` is false.
     #[tracing::instrument(
         "minecraft_chart/release",
         level = Level::TRACE,
         skip(self),
         fields(minecraft_chart_name = %self.name)
     )]
-    pub(crate) async fn release(&mut self) -> Result<(), ArgoCdError> {
-        if let Some(Ok(tear)) = self.argocd_tear.take() {
-            tear.close().await
-        } else {
-            Ok(())
+    pub(crate) async fn release(&mut self, automated_sync: bool) -> Result<(), ArgoCdError> {
+        match self.argocd_tear.take() {
+            Some(Ok(tear)) if automated_sync => tear.close().await,
+            Some(Ok(tear)) => tear.close_without_automated_sync().await,
+            _ => Ok(()),
         }
     }
 }
@@ -1,8 +1,22 @@
 pub(crate) mod argocd;
+pub(crate) mod configmap;
 pub(crate) mod custom_job;
+pub(crate) mod diagnostics;
+pub(crate) mod event;
+pub(crate) mod event_recorder;
+pub(crate) mod hook;
 pub(crate) mod job;
+pub(crate) mod job_logs;
 pub(crate) mod minecraft_chart;
+pub(crate) mod pod;
 pub(crate) mod statefulset;
+pub(crate) mod watch;
 
 pub(crate) const MANAGEER_ROLE_NAME: &str = "man10routine";
 pub(crate) const ARGOCD_NAMESPACE: &str = "argocd";
+
+pub(crate) const MANAGED_BY_LABEL: &str = "app.kubernetes.io/managed-by";
+pub(crate) const RUN_ID_LABEL: &str = "man10routine/run-id";
+pub(crate) const CHART_LABEL: &str = "man10routine/chart";
+pub(crate) const JOB_LABEL: &str = "man10routine/job";
+pub(crate) const ATTEMPT_LABEL: &str = "man10routine/attempt";
@@ -0,0 +1,227 @@
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Status;
+use kube::api::AttachParams;
+use kube::{Api, Client};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::{debug, instrument};
+
+#[derive(Error, Debug)]
+pub enum PodExecError {
+    #[error("Kubernetes client error: {0}")]
+    KubeClient(#[from] kube::Error),
+
+    #[error("Attached process error: {0}")]
+    Attach(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("Failed to read output of the command: {0}")]
+    Output(#[from] std::io::Error),
+}
+
+/// Container waiting reasons which will not resolve without intervention
+const FATAL_WAITING_REASONS: [&str; 5] = [
+    "ImagePullBackOff",
+    "CrashLoopBackOff",
+    "CreateContainerConfigError",
+    "CreateContainerError",
+    "InvalidImageName",
+];
+
+/// Unrecoverable failure of a pod detected from its status or events
+#[derive(Debug, Clone)]
+pub struct PodFailure {
+    pub object_name: String,
+    pub reason: String,
+    pub message: String,
+}
+
+/// Detects unrecoverable failures from the pod status, such as crash loops or image pull errors.
+pub(crate) fn pod_failure(pod: &Pod) -> Option<PodFailure> {
+    let pod_name = pod.metadata.name.clone().unwrap_or_default();
+    let status = pod.status.as_ref()?;
+
+    let container_statuses = status
+        .init_container_statuses
+        .iter()
+        .flatten()
+        .chain(status.container_statuses.iter().flatten());
+    for container_status in container_statuses {
+        let Some(waiting) = container_status
+            .state
+            .as_ref()
+            .and_then(|s| s.waiting.as_ref())
+        else {
+            continue;
+        };
+        if let Some(reason) = waiting
+            .reason
+            .as_deref()
+            .filter(|r| FATAL_WAITING_REASONS.contains(r))
+        {
+            return Some(PodFailure {
+                object_name: pod_name,
+                reason: reason.to_string(),
+                message: format!(
+                    "container '{}': {}",
+                    container_status.name,
+                    waiting.message.as_deref().unwrap_or("")
+                ),
+            });
+        }
+    }
+
+    status
+        .conditions
+        .iter()
+        .flatten()
+        .find(|c| {
+            c.type_ == "PodScheduled"
+                && c.status == "False"
+                && c.reason.as_deref() == Some("Unschedulable")
+        })
+        .map(|c| PodFailure {
+            object_name: pod_name,
+            reason: "Unschedulable".to_string(),
+            message: c.message.clone().unwrap_or_default(),
+        })
+}
+
+/// Output of a command executed in a pod
+#[derive(Debug, Clone)]
+pub(crate) struct ExecOutput {
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+    pub(crate) status: Option<Status>,
+}
+
+impl ExecOutput {
+    /// Whether the command exited with status 0
+    pub(crate) fn success(&self) -> bool {
+        self.status.as_ref().and_then(|s| s.status.as_deref()) == Some("Success")
+    }
+
+    /// Human-readable reason of the failure, if the command failed
+    pub(crate) fn failure_message(&self) -> Option<String> {
+        if self.success() {
+            return None;
+        }
+        Some(
+            self.status
+                .as_ref()
+                .and_then(|s| s.message.clone())
+                .unwrap_or_else(|| "command exited without status".to_string()),
+        )
+    }
+}
+
+/// Executes `command` in `container` of `pod_name` and collects its output and exit status.
+#[instrument("exec_in_pod", skip(client, command), fields(command = ?command))]
+pub(crate) async fn exec_in_pod(
+    client: Client,
+    namespace: &str,
+    pod_name: &str,
+    container: &str,
+    command: Vec<String>,
+) -> Result<ExecOutput, PodExecError> {
+    let pod_api: Api<Pod> = Api::namespaced(client, namespace);
+    let mut attached = pod_api
+        .exec(
+            pod_name,
+            command,
+            &AttachParams::default().container(container),
+        )
+        .await?;
+
+    let status = attached
+        .take_status()
+        .expect("status of an attached process is taken only once");
+    let (stdout, stderr) = tokio::try_join!(
+        read_to_string(attached.stdout()),
+        read_to_string(attached.stderr())
+    )?;
+    let status = status.await;
+    attached
+        .join()
+        .await
+        .map_err(|e| PodExecError::Attach(Box::new(e)))?;
+
+    debug!("Command finished with status {:?}", status);
+    Ok(ExecOutput {
+        stdout,
+        stderr,
+        status,
+    })
+}
+
+async fn read_to_string(reader: Option<impl AsyncRead + Unpin>) -> std::io::Result<String> {
+    let mut buf = String::new();
+    if let Some(mut reader) = reader {
+        reader.read_to_string(&mut buf).await?;
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::core::v1::{
+        ContainerState, ContainerStateWaiting, ContainerStatus, PodCondition, PodStatus,
+    };
+    use kube::api::ObjectMeta;
+
+    fn pod(status: PodStatus) -> Pod {
+        Pod {
+            metadata: ObjectMeta {
+                name: Some("mcserver-lobby-0".to_string()),
+                ..Default::default()
+            },
+            status: Some(status),
+            ..Default::default()
+        }
+    }
+
+    fn waiting_container(reason: &str) -> ContainerStatus {
+        ContainerStatus {
+            name: "mcserver".to_string(),
+            state: Some(ContainerState {
+                waiting: Some(ContainerStateWaiting {
+                    reason: Some(reason.to_string()),
+                    message: Some("back-off restarting failed container".to_string()),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pod_failure() {
+        let starting = pod(PodStatus {
+            container_statuses: Some(vec![waiting_container("ContainerCreating")]),
+            ..Default::default()
+        });
+        assert!(pod_failure(&starting).is_none());
+
+        let crashing = pod(PodStatus {
+            container_statuses: Some(vec![waiting_container("CrashLoopBackOff")]),
+            ..Default::default()
+        });
+        let failure = pod_failure(&crashing).unwrap();
+        assert_eq!(failure.object_name, "mcserver-lobby-0");
+        assert_eq!(failure.reason, "CrashLoopBackOff");
+
+        let unschedulable = pod(PodStatus {
+            conditions: Some(vec![PodCondition {
+                type_: "PodScheduled".to_string(),
+                status: "False".to_string(),
+                reason: Some("Unschedulable".to_string()),
+                message: Some("0/3 nodes are available".to_string()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        });
+        let failure = pod_failure(&unschedulable).unwrap();
+        assert_eq!(failure.reason, "Unschedulable");
+        assert_eq!(failure.message, "0/3 nodes are available");
+    }
+}
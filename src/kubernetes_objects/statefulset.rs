@@ -1,6 +1,9 @@
-use k8s_openapi::api::apps::v1::{StatefulSet, StatefulSetStatus};
-use kube::api::{Patch, PatchParams};
+use k8s_openapi::api::apps::v1::{StatefulSet, StatefulSetSpec, StatefulSetStatus};
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::chrono::{DateTime, Utc};
+use kube::api::{ListParams, Patch, PatchParams};
 use kube::{Api, Client};
+use tokio::time::Instant;
 use tracing::{Instrument, warn};
 use tracing::{error, info, instrument, trace_span};
 use tracing_error::{ExtractSpanTrace, SpanTrace};
@@ -8,6 +11,9 @@ use tracing_error::{ExtractSpanTrace, SpanTrace};
 use crate::config::polling::PollingConfig;
 use crate::error::{SpannedErr, SpannedExt};
 use crate::kubernetes_objects::MANAGEER_ROLE_NAME;
+use crate::kubernetes_objects::event::{fatal_event, list_object_events};
+use crate::kubernetes_objects::pod::{PodFailure, pod_failure};
+use crate::kubernetes_objects::watch::ObjectWatcher;
 
 #[derive(thiserror::Error, Debug)]
 pub enum StatefulSetScaleError {
@@ -45,6 +51,9 @@ pub enum WaitStatefulSetScaleError {
 
     #[error("StatefulSet did not met condition within {0} seconds timeout")]
     StatefulSetScaledCheckTimeout(u64),
+
+    #[error("'{}' failed with {}: {}", .0.object_name, .0.reason, .0.message)]
+    PodFailed(PodFailure),
 }
 
 #[instrument(
@@ -131,57 +140,32 @@ pub(crate) async fn wait_until_statefulset_scaled(
     polling_config: &PollingConfig,
 ) -> Result<StatefulSetStatus, SpannedErr<WaitStatefulSetScaleError>> {
     info!(
-        "Waiting {} to {} seconds for statefulset '{}' to be scaled...",
-        polling_config.initial_wait.as_secs(),
+        "Waiting up to {} seconds for statefulset '{}' to be scaled...",
         polling_config.max_wait.as_secs(),
         statefulset_name
     );
-    tokio::time::sleep(polling_config.initial_wait).await;
-    let mut wait_duration = polling_config.initial_wait;
+    let started_at = Utc::now();
+    let started_instant = Instant::now();
     let mut errors_count = 0u64;
-    let statefulset_api: Api<StatefulSet> = Api::namespaced(client, namespace);
+    let statefulset_api: Api<StatefulSet> = Api::namespaced(client.clone(), namespace);
+    let mut watcher = ObjectWatcher::new(statefulset_api, statefulset_name, polling_config);
+    let mut last_state: Option<(StatefulSetStatus, Option<StatefulSetSpec>)> = None;
+    // Pods and events are listed at most once per `poll_interval`, however often the watch
+    // reports a change
+    let mut next_inspection = started_instant;
     loop {
-        match statefulset_api.get(statefulset_name).await {
-            Ok(StatefulSet {
+        match watcher.next().await {
+            Ok(Some(StatefulSet {
                 status: Some(status),
+                spec,
                 ..
-            }) => {
-                if status.current_replicas.unwrap_or(0) == target_replicas
-                    && status.available_replicas.unwrap_or(0) == target_replicas
-                {
-                    info!(
-                        "StatefulSet '{}' has been scaled to {} replicas after {} seconds.",
-                        statefulset_name,
-                        target_replicas,
-                        wait_duration.as_secs()
-                    );
-                    break Ok(status);
-                }
-
-                info!(
-                    "StatefulSet '{}' still scaling after {} seconds (current status: {:?}). Waiting another {} seconds...",
-                    statefulset_name,
-                    wait_duration.as_secs(),
-                    status,
-                    polling_config.poll_interval.as_secs()
-                );
-                if wait_duration >= polling_config.max_wait {
-                    error!(
-                        "Waited more than {} seconds for statefulset '{}' to be scaled.",
-                        wait_duration.as_secs(),
-                        statefulset_name
-                    );
-                    break Err(WaitStatefulSetScaleError::StatefulSetScaledCheckTimeout(
-                        wait_duration.as_secs(),
-                    ))
-                    .with_span_trace();
-                }
-                wait_duration += polling_config.poll_interval;
-                tokio::time::sleep(polling_config.poll_interval).await;
+            })) => {
+                last_state = Some((status, spec));
             }
-            Ok(_) => {
+            Ok(Some(_)) => {
                 break Err(WaitStatefulSetScaleError::StatefulSetHasNoStatus).with_span_trace();
             }
+            Ok(None) => {}
             Err(e) => {
                 warn!(
                     "Error while checking statefulset '{}': {}",
@@ -199,9 +183,111 @@ pub(crate) async fn wait_until_statefulset_scaled(
                     );
                     break Err(WaitStatefulSetScaleError::KubeClient(e)).with_span_trace();
                 }
-                wait_duration += polling_config.error_wait;
                 tokio::time::sleep(polling_config.error_wait).await;
+                continue;
             }
         }
+
+        let wait_duration = started_instant.elapsed();
+        if let Some((status, spec)) = &last_state {
+            if status.current_replicas.unwrap_or(0) == target_replicas
+                && status.available_replicas.unwrap_or(0) == target_replicas
+            {
+                info!(
+                    "StatefulSet '{}' has been scaled to {} replicas after {} seconds.",
+                    statefulset_name,
+                    target_replicas,
+                    wait_duration.as_secs()
+                );
+                break Ok(status.clone());
+            }
+
+            if target_replicas > 0 && Instant::now() >= next_inspection {
+                next_inspection = Instant::now() + polling_config.poll_interval;
+                match detect_statefulset_failure(
+                    client.clone(),
+                    namespace,
+                    statefulset_name,
+                    spec.as_ref(),
+                    started_at,
+                )
+                .await
+                {
+                    Ok(Some(failure)) => {
+                        error!(
+                            "StatefulSet '{}' cannot become ready: '{}' failed with {}: {}",
+                            statefulset_name, failure.object_name, failure.reason, failure.message
+                        );
+                        break Err(WaitStatefulSetScaleError::PodFailed(failure)).with_span_trace();
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!(
+                            "Failed to inspect pods of statefulset '{}': {}",
+                            statefulset_name, e
+                        );
+                    }
+                }
+            }
+
+            info!(
+                "StatefulSet '{}' still scaling after {} seconds (current status: {:?}).",
+                statefulset_name,
+                wait_duration.as_secs(),
+                status,
+            );
+        }
+        if wait_duration >= polling_config.max_wait {
+            error!(
+                "Waited more than {} seconds for statefulset '{}' to be scaled.",
+                wait_duration.as_secs(),
+                statefulset_name
+            );
+            break Err(WaitStatefulSetScaleError::StatefulSetScaledCheckTimeout(
+                wait_duration.as_secs(),
+            ))
+            .with_span_trace();
+        }
+    }
+}
+
+/// Inspects the pods and events of the StatefulSet for failures that will not resolve by waiting.
+async fn detect_statefulset_failure(
+    client: Client,
+    namespace: &str,
+    statefulset_name: &str,
+    spec: Option<&StatefulSetSpec>,
+    since: DateTime<Utc>,
+) -> Result<Option<PodFailure>, kube::Error> {
+    let statefulset_events =
+        list_object_events(client.clone(), namespace, "StatefulSet", statefulset_name).await?;
+    if let Some(failure) = fatal_event(&statefulset_events, since) {
+        return Ok(Some(failure));
+    }
+
+    let Some(match_labels) = spec.and_then(|s| s.selector.match_labels.as_ref()) else {
+        return Ok(None);
+    };
+    let selector = match_labels
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join(",");
+    let pod_api: Api<Pod> = Api::namespaced(client.clone(), namespace);
+    let pods = pod_api
+        .list(&ListParams::default().labels(&selector))
+        .await?;
+
+    for pod in pods.items.iter() {
+        if let Some(failure) = pod_failure(pod) {
+            return Ok(Some(failure));
+        }
+        let pod_name = pod.metadata.name.as_deref().unwrap_or_default();
+        let pod_events = list_object_events(client.clone(), namespace, "Pod", pod_name).await?;
+        if let Some(failure) = fatal_event(&pod_events, since) {
+            return Ok(Some(failure));
+        }
     }
+
+    Ok(None)
 }
@@ -0,0 +1,80 @@
+use std::fmt::Debug;
+use std::time::Duration;
+
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use kube::runtime::watcher;
+use kube::{Api, Resource};
+use serde::de::DeserializeOwned;
+use tokio::time::{Instant, sleep, timeout_at};
+use tracing::warn;
+
+use crate::config::polling::PollingConfig;
+
+/// Follows the state of a single object with a watch, falling back to polling when the watch
+/// breaks or when watching is disabled in the [`PollingConfig`].
+pub(crate) struct ObjectWatcher<K> {
+    api: Api<K>,
+    name: String,
+    poll_interval: Duration,
+    stream: Option<BoxStream<'static, Result<Option<K>, watcher::Error>>>,
+    next_poll_delay: Duration,
+}
+
+impl<K> ObjectWatcher<K>
+where
+    K: Resource + Clone + DeserializeOwned + Debug + Send + 'static,
+{
+    pub(crate) fn new(api: Api<K>, name: &str, polling_config: &PollingConfig) -> Self {
+        let stream = polling_config
+            .watch
+            .then(|| watcher::watch_object(api.clone(), name).boxed());
+        Self {
+            api,
+            name: name.to_string(),
+            poll_interval: polling_config.poll_interval,
+            stream,
+            next_poll_delay: polling_config.initial_wait,
+        }
+    }
+
+    /// Waits for the next state of the object.
+    ///
+    /// While watching, returns `Ok(None)` if the object did not change within `poll_interval`.
+    /// While polling, the object is fetched after `initial_wait` the first time and after
+    /// `poll_interval` afterwards.
+    pub(crate) async fn next(&mut self) -> Result<Option<K>, kube::Error> {
+        let deadline = Instant::now() + self.poll_interval;
+
+        if let Some(stream) = self.stream.as_mut() {
+            match timeout_at(deadline, stream.next()).await {
+                Ok(Some(Ok(Some(object)))) => return Ok(Some(object)),
+                Err(_) => return Ok(None),
+                Ok(Some(Ok(None))) => {
+                    warn!(
+                        "Watched object '{}' was not found. Falling back to polling...",
+                        self.name
+                    );
+                }
+                Ok(Some(Err(e))) => {
+                    warn!(
+                        "Watch of '{}' broke: {}. Falling back to polling...",
+                        self.name, e
+                    );
+                }
+                Ok(None) => {
+                    warn!("Watch of '{}' ended. Falling back to polling...", self.name);
+                }
+            }
+            self.stream = None;
+            self.next_poll_delay = Duration::ZERO;
+        }
+
+        sleep(std::mem::replace(
+            &mut self.next_poll_delay,
+            self.poll_interval,
+        ))
+        .await;
+        self.api.get(&self.name).await.map(Some)
+    }
+}
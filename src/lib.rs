@@ -1,6 +1,7 @@
 use self::cli::{Cli, Routine};
+use self::progress::Progress;
 use self::routine::daily::DailyRoutineContext;
-use clap::Parser;
+use self::routine::daily::report::ReportOutput;
 use thiserror::Error;
 use tracing::info;
 use tracing_error::ExtractSpanTrace;
@@ -9,9 +10,16 @@ use tracing_error::SpanTrace;
 pub mod cli;
 pub mod config;
 pub mod error;
+pub(crate) mod history;
+pub(crate) mod http;
 pub mod kubernetes_objects;
+pub mod logging;
+pub(crate) mod metrics;
+pub(crate) mod notification;
+pub mod progress;
 pub(crate) mod routine;
 pub mod scheduler;
+pub mod telemetry;
 
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -21,6 +29,18 @@ pub enum AppError {
     #[error("Failed to initialize kubernetes client.\n{0}")]
     KubeClientError(#[from] kube::Error),
 
+    #[error("Rolling restart requires the 'rolling' section in config")]
+    RollingNotConfigured,
+
+    #[error("Rolling restart requires a wave size of at least 1")]
+    RollingWaveSizeZero,
+
+    #[error("History command requires the 'history' section in config")]
+    HistoryNotConfigured,
+
+    #[error("Failed to load the run history.\n{0}")]
+    HistoryError(#[from] history::HistoryError),
+
     #[error("Daily routine stopped due to following error:\n{0}")]
     DailyRoutineError(#[from] crate::routine::daily::error::DailyRoutineError),
 }
@@ -29,25 +49,54 @@ impl ExtractSpanTrace for AppError {
     fn span_trace(&self) -> Option<&SpanTrace> {
         match self {
             AppError::DailyRoutineError(e) => e.span_trace(),
+            AppError::HistoryError(history::HistoryError::KubeClient(e)) => e.span_trace(),
             _ => None,
         }
     }
 }
 
-pub async fn app() -> Result<(), AppError> {
-    let cli = Cli::parse();
-    let config = config::Config::new_from_file(&cli.config).await?;
-
-    info!("Config Loaded.");
-
+pub async fn app(cli: Cli, progress: Option<Progress>) -> Result<(), AppError> {
     let client = kube::Client::try_default().await?;
 
     info!("Kubernetes Client Initialized.");
 
+    let config = config::Config::new_from_file(&cli.config, &client).await?;
+
+    info!("Config Loaded.");
+
+    let report_output = ReportOutput {
+        path: cli.report,
+        configmap: cli.report_configmap,
+    };
+
     match cli.routine {
         Routine::Daily {} => {
-            let context = DailyRoutineContext::new(config, client);
-            context.run().await?;
+            let context = DailyRoutineContext::new(config, client, progress);
+            let result = context.run().await;
+            context.report("daily", &result, &report_output).await;
+            result?;
+        }
+        Routine::Rolling { wave_size } => {
+            if config.rolling.is_none() {
+                return Err(AppError::RollingNotConfigured);
+            }
+            if wave_size == Some(0) {
+                return Err(AppError::RollingWaveSizeZero);
+            }
+            let context = DailyRoutineContext::new(config, client, progress);
+            let result = context.run_rolling(wave_size).await;
+            context.report("rolling", &result, &report_output).await;
+            result?;
+        }
+        Routine::History { server } => {
+            let Some(history_config) = &config.history else {
+                return Err(AppError::HistoryNotConfigured);
+            };
+            let runs = history::load_history(client, &config.namespace, history_config).await?;
+            print!(
+                "{}",
+                history::format_history(&runs, server.as_deref(), config.time_zone)
+            );
         }
     }
 
@@ -0,0 +1,157 @@
+use std::fmt::Display;
+use std::io::Write;
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+use tracing::error;
+use tracing_error::SpanTrace;
+use tracing_subscriber::filter::{EnvFilter, FilterExt, filter_fn};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::{Layer, Registry};
+
+/// Format of the log lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LogFormat {
+    /// Human-oriented lines, with span traces of errors printed in color to stderr
+    #[default]
+    Text,
+
+    /// One JSON object per line, carrying the fields of the enclosing spans and the span traces
+    /// of errors
+    Json,
+}
+
+/// Format given to [`layer`], which decides how span traces are emitted
+static FORMAT: OnceLock<LogFormat> = OnceLock::new();
+
+/// Writer given to [`layer_to`] for the colored span traces of the text format
+static SPAN_TRACE_WRITER: OnceLock<BoxMakeWriter> = OnceLock::new();
+
+/// Layer writing the events enabled by `RUST_LOG` to stdout in `format`.
+pub fn layer(format: LogFormat) -> Box<dyn Layer<Registry> + Send + Sync> {
+    layer_to(format, std::io::stdout, std::io::stderr)
+}
+
+/// Same as [`layer`], writing to `writer` instead of stdout and printing the span traces of
+/// [`log_error`] to `error_writer` instead of stderr.
+pub fn layer_to<W, E>(
+    format: LogFormat,
+    writer: W,
+    error_writer: E,
+) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    E: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let _ = FORMAT.set(format);
+    let _ = SPAN_TRACE_WRITER.set(BoxMakeWriter::new(error_writer));
+    layer_with_writer(format, EnvFilter::from_default_env(), writer)
+}
+
+fn layer_with_writer<W>(
+    format: LogFormat,
+    filter: EnvFilter,
+    writer: W,
+) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .with_thread_ids(true)
+            .with_target(false)
+            .with_writer(writer)
+            .with_filter(filter)
+            .boxed(),
+        // The spans of this crate are mostly `trace` ones, which are kept whatever `RUST_LOG`
+        // says so that their fields, such as `mcserver_name`, are on every event
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_thread_ids(true)
+            .with_current_span(true)
+            .with_span_list(true)
+            .flatten_event(true)
+            .with_writer(writer)
+            .with_filter(filter.or(filter_fn(|metadata| {
+                metadata.is_span() && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+            })))
+            .boxed(),
+    }
+}
+
+/// Logs the error along with its span trace: as a `span_trace` field in JSON, or printed in
+/// color to the error writer given to [`layer_to`] otherwise.
+pub fn log_error(error: &dyn Display, span_trace: Option<&SpanTrace>) {
+    match FORMAT.get().copied().unwrap_or_default() {
+        LogFormat::Json => match span_trace {
+            Some(span_trace) => error!(span_trace = %span_trace, "{error}"),
+            None => error!("{error}"),
+        },
+        LogFormat::Text => {
+            error!("{error}");
+            if let Some(span_trace) = span_trace {
+                let span_trace = format!("\n{}\n\n", color_spantrace::colorize(span_trace));
+                let _ = match SPAN_TRACE_WRITER.get() {
+                    Some(writer) => writer.make_writer().write_all(span_trace.as_bytes()),
+                    None => std::io::stderr().write_all(span_trace.as_bytes()),
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{info, trace_span};
+    use tracing_subscriber::prelude::*;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_events_carry_span_fields() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let layer = layer_with_writer(LogFormat::Json, EnvFilter::new("info"), move || {
+            writer.clone()
+        });
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let _task = trace_span!("flight_task", task_name = "relaunch_mcserver/lobby").entered();
+            let _relaunch = trace_span!("relaunch_mcserver", mcserver_name = "lobby").entered();
+            let _ignored = trace_span!(target: "kube_client", "request").entered();
+            info!(replicas = 1, "Relaunched.");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+        assert_eq!(line["message"], "Relaunched.");
+        assert_eq!(line["replicas"], 1);
+        assert_eq!(line["span"]["mcserver_name"], "lobby");
+        let spans: Vec<&str> = line["spans"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(spans, vec!["flight_task", "relaunch_mcserver"]);
+        assert_eq!(line["spans"][0]["task_name"], "relaunch_mcserver/lobby");
+    }
+}
@@ -1,44 +1,68 @@
 use std::panic;
 
+use clap::Parser;
 use man10_routine::app;
-use tracing::error;
+use man10_routine::cli::Cli;
+use man10_routine::logging::{self, LogFormat};
+use man10_routine::progress::Progress;
+use man10_routine::telemetry::Telemetry;
+use tracing::{info, warn};
 use tracing_error::ErrorLayer;
 use tracing_error::ExtractSpanTrace;
 use tracing_error::SpanTrace;
-use tracing_subscriber::EnvFilter;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::util::SubscriberInitExt;
 
 #[tokio::main]
 async fn main() {
-    let fmt_layer = tracing_subscriber::fmt::layer()
-        .with_thread_ids(true)
-        .with_target(false)
-        .with_filter(EnvFilter::from_default_env());
+    let cli = Cli::parse();
+
+    let log_format = cli.log_format();
+    let progress = cli.progress().then(Progress::new).flatten();
+    let log_layer = match &progress {
+        Some(progress) => logging::layer_to(log_format, progress.writer(), progress.error_writer()),
+        None => logging::layer(log_format),
+    };
+
+    let telemetry = cli.otlp_endpoint().map(Telemetry::new);
+    let (telemetry, telemetry_error) = match telemetry {
+        Some(Ok(telemetry)) => (Some(telemetry), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
 
     tracing_subscriber::registry()
-        .with(fmt_layer)
+        .with(log_layer)
+        .with(progress.as_ref().map(|p| p.layer()))
+        .with(telemetry.as_ref().map(|t| t.layer()))
         .with(ErrorLayer::default())
         .init();
 
+    if let Some(e) = telemetry_error {
+        warn!("Spans will not be exported: {e}");
+    }
+
+    if cli.progress() && progress.is_none() {
+        info!("Stdout is not a terminal, so the progress is logged instead.");
+    }
+
     panic::set_hook(Box::new(move |info| {
-        error!("{}", info);
-        let span_trace = SpanTrace::capture();
-        eprintln!("\n{}\n", color_spantrace::colorize(&span_trace));
+        logging::log_error(info, Some(&SpanTrace::capture()));
     }));
 
-    match app().await {
-        Ok(_) => {
-            std::process::exit(0);
-        }
+    let code = match app(cli, progress).await {
+        Ok(_) => 0,
         Err(e) => {
-            error!("{e}");
-            if let Some(span_trace) = e.span_trace() {
-                eprintln!("\n{}\n", color_spantrace::colorize(span_trace));
-            } else {
+            logging::log_error(&e, e.span_trace());
+            if e.span_trace().is_none() && log_format == LogFormat::Text {
                 eprintln!("\nNo span trace available.\n");
             }
-            std::process::exit(1);
+            1
         }
+    };
+
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown().await;
     }
+    std::process::exit(code);
 }
@@ -0,0 +1,453 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use hyper::{Method, Uri};
+use thiserror::Error;
+use tracing::{info, instrument};
+
+use crate::config::metrics::MetricsConfig;
+use crate::http::{HttpError, fetch, http_client, send};
+use crate::routine::daily::report::{RunReport, TaskStatus};
+
+/// Gauge which alerts watch for staleness, kept across failed runs
+const LAST_SUCCESS_METRIC: &str = "man10routine_last_success_timestamp";
+
+/// Time allowed for the Pushgateway to accept the metrics
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum MetricsError {
+    #[error("Metrics cannot be written to {0}: {1}")]
+    WriteFile(PathBuf, std::io::Error),
+
+    #[error("Metrics cannot be pushed to the Pushgateway: {0}")]
+    Push(#[from] HttpError),
+
+    #[error("Pushgateway did not respond within {0:?}")]
+    PushTimeout(Duration),
+}
+
+/// Writes the metrics of the run to the textfile and pushes them to the Pushgateway.
+#[instrument("export_metrics", skip(config, report), fields(run_id = %report.run_id))]
+pub(crate) async fn export_metrics(
+    config: &MetricsConfig,
+    report: &RunReport,
+) -> Result<(), MetricsError> {
+    if let Some(path) = &config.textfile {
+        write_textfile(path, report).await?;
+        info!("Run metrics written to {}.", path.display());
+    }
+
+    if let Some(base) = &config.pushgateway {
+        let base = base.trim_end_matches('/');
+        let url: Uri = format!(
+            "{}/metrics/job/{}/routine/{}",
+            base, config.job, report.routine
+        )
+        .parse()
+        .expect("the Pushgateway URL has been validated while parsing the config");
+        let exposed: Uri = format!("{base}/metrics")
+            .parse()
+            .expect("the Pushgateway URL has been validated while parsing the config");
+        let http = http_client();
+        let headers = BTreeMap::new();
+
+        // PUT replaces the whole group so that no family of an earlier run is left behind. The
+        // last success of the routine is read back from the Pushgateway to keep it after a
+        // failed run
+        let push = async {
+            let previous = fetch(&http, &exposed).await?;
+            let previous = pushed_last_success(&previous, &config.job, &report.routine)
+                .map(|value| (report.routine.clone(), value))
+                .into_iter()
+                .collect();
+            let body = render(report, &previous).into_bytes();
+            send(
+                &http,
+                Method::PUT,
+                &url,
+                &headers,
+                "text/plain; version=0.0.4",
+                body,
+            )
+            .await
+        };
+        tokio::time::timeout(PUSH_TIMEOUT, push)
+            .await
+            .map_err(|_| MetricsError::PushTimeout(PUSH_TIMEOUT))??;
+        info!("Run metrics pushed to {}.", url);
+    }
+
+    Ok(())
+}
+
+/// Replaces the textfile atomically, keeping the last success of every routine from the
+/// previous file.
+async fn write_textfile(path: &Path, report: &RunReport) -> Result<(), MetricsError> {
+    let previous = match tokio::fs::read_to_string(path).await {
+        Ok(previous) => previous,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(MetricsError::WriteFile(path.to_path_buf(), e)),
+    };
+    let metrics = render(report, &last_successes(&previous));
+
+    // node-exporter only reads `*.prom` files, so the temporary file is never collected
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    tokio::fs::write(&tmp, metrics)
+        .await
+        .map_err(|e| MetricsError::WriteFile(tmp.clone(), e))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .map_err(|e| MetricsError::WriteFile(path.to_path_buf(), e))
+}
+
+/// Last success of each routine found in a previously written textfile
+fn last_successes(textfile: &str) -> BTreeMap<String, String> {
+    textfile
+        .lines()
+        .filter_map(|line| {
+            let rest = line
+                .strip_prefix(LAST_SUCCESS_METRIC)?
+                .strip_prefix("{routine=\"")?;
+            let (routine, value) = rest.split_once("\"} ")?;
+            Some((routine.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Last success of the routine in the group `job`/`routine` of the metrics exposed by a
+/// Pushgateway, whose samples also carry the grouping labels and an `instance` label.
+fn pushed_last_success(exposition: &str, job: &str, routine: &str) -> Option<String> {
+    let job = format!("job=\"{job}\"");
+    let routine = format!("routine=\"{routine}\"");
+    exposition.lines().find_map(|line| {
+        let rest = line.strip_prefix(LAST_SUCCESS_METRIC)?.strip_prefix('{')?;
+        let (labels, value) = rest.split_once("} ")?;
+        let labels: Vec<&str> = labels.split(',').collect();
+        (labels.contains(&job.as_str()) && labels.contains(&routine.as_str()))
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// Renders the run in the Prometheus text exposition format.
+///
+/// `previous` holds the last success of other routines, or of this routine when the run failed.
+fn render(report: &RunReport, previous: &BTreeMap<String, String>) -> String {
+    let routine = report.routine.as_str();
+    let finished_at = report.finished_at.timestamp().to_string();
+    let mut output = String::new();
+
+    gauge(
+        &mut output,
+        "man10routine_last_run_timestamp",
+        "Time at which the last run finished.",
+        [(labels(&[("routine", routine)]), finished_at.clone())],
+    );
+    gauge(
+        &mut output,
+        "man10routine_last_run_success",
+        "Whether the last run succeeded.",
+        [(labels(&[("routine", routine)]), flag(report.succeeded))],
+    );
+
+    let mut last_successes = previous.clone();
+    if report.succeeded {
+        last_successes.insert(routine.to_string(), finished_at);
+    }
+    gauge(
+        &mut output,
+        LAST_SUCCESS_METRIC,
+        "Time at which the last successful run finished.",
+        last_successes
+            .iter()
+            .map(|(routine, value)| (labels(&[("routine", routine)]), value.clone())),
+    );
+
+    gauge(
+        &mut output,
+        "man10routine_run_duration_seconds",
+        "Duration of the last run.",
+        [(
+            labels(&[("routine", routine)]),
+            seconds(report.finished_at - report.started_at),
+        )],
+    );
+    gauge(
+        &mut output,
+        "man10routine_task_duration_seconds",
+        "Duration of each task of the last run which finished or was cancelled.",
+        report.tasks.iter().filter_map(|task| {
+            let duration = task.duration_seconds?;
+            let labels = labels(&[("routine", routine), ("task", &task.name)]);
+            Some((labels, duration.to_string()))
+        }),
+    );
+    gauge(
+        &mut output,
+        "man10routine_task_success",
+        "Whether each task of the last run succeeded, except those skipped.",
+        report
+            .tasks
+            .iter()
+            .filter(|task| task.status != TaskStatus::Skipped)
+            .map(|task| {
+                let labels = labels(&[("routine", routine), ("task", &task.name)]);
+                (labels, flag(task.status == TaskStatus::Succeeded))
+            }),
+    );
+    gauge(
+        &mut output,
+        "man10routine_task_skipped",
+        "Whether each task of the last run was skipped by the `when` condition of its job.",
+        report.tasks.iter().map(|task| {
+            let labels = labels(&[("routine", routine), ("task", &task.name)]);
+            (labels, flag(task.status == TaskStatus::Skipped))
+        }),
+    );
+    gauge(
+        &mut output,
+        "man10routine_server_downtime_seconds",
+        "Time each server was down during the last run.",
+        report.downtime.iter().map(|downtime| {
+            let labels = labels(&[("routine", routine), ("server", &downtime.server)]);
+            (labels, downtime.downtime_seconds.to_string())
+        }),
+    );
+
+    // Last attempt of each job
+    let mut jobs: BTreeMap<(&str, &str), (u32, bool)> = BTreeMap::new();
+    for job in &report.jobs {
+        let key = (job.chart_name.as_str(), job.job_name.as_str());
+        let last = jobs.entry(key).or_insert((job.attempt, job.succeeded));
+        if job.attempt >= last.0 {
+            *last = (job.attempt, job.succeeded);
+        }
+    }
+    let job_labels =
+        |chart: &str, job: &str| labels(&[("routine", routine), ("chart", chart), ("job", job)]);
+    gauge(
+        &mut output,
+        "man10routine_job_success",
+        "Whether the last attempt of each Kubernetes Job of the last run succeeded.",
+        jobs.iter()
+            .map(|((chart, job), (_, succeeded))| (job_labels(chart, job), flag(*succeeded))),
+    );
+    gauge(
+        &mut output,
+        "man10routine_job_attempts",
+        "Number of attempts of each Kubernetes Job of the last run.",
+        jobs.iter()
+            .map(|((chart, job), (attempts, _))| (job_labels(chart, job), attempts.to_string())),
+    );
+
+    let restore_failures = report
+        .argocd_applications
+        .iter()
+        .filter(|application| !application.restored)
+        .count();
+    gauge(
+        &mut output,
+        "man10routine_argocd_restore_failures",
+        "Number of ArgoCD Applications torn down by the last run and not restored.",
+        [(
+            labels(&[("routine", routine)]),
+            restore_failures.to_string(),
+        )],
+    );
+
+    output
+}
+
+/// Writes a gauge family, or nothing when it has no samples.
+fn gauge(
+    output: &mut String,
+    name: &str,
+    help: &str,
+    samples: impl IntoIterator<Item = (String, String)>,
+) {
+    let mut samples = samples.into_iter().peekable();
+    if samples.peek().is_none() {
+        return;
+    }
+    let _ = writeln!(output, "# HELP {name} {help}");
+    let _ = writeln!(output, "# TYPE {name} gauge");
+    for (labels, value) in samples {
+        let _ = writeln!(output, "{name}{labels} {value}");
+    }
+}
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let pairs: Vec<String> = pairs
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', r"\\")
+                .replace('"', r#"\""#)
+                .replace('\n', r"\n");
+            format!("{name}=\"{value}\"")
+        })
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+fn flag(value: bool) -> String {
+    if value { "1" } else { "0" }.to_string()
+}
+
+fn seconds(duration: k8s_openapi::chrono::TimeDelta) -> String {
+    (duration.num_milliseconds() as f64 / 1000.0).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::tests::stand_in;
+    use crate::routine::daily::report::{ArgoCdReport, DowntimeReport, JobReport, TaskReport};
+    use k8s_openapi::chrono::DateTime;
+
+    fn report(succeeded: bool) -> RunReport {
+        let at = |seconds: i64| DateTime::from_timestamp(1_800_000_000 + seconds, 0).unwrap();
+        let job = |attempt: u32, succeeded: bool| JobReport {
+            name: format!("mcserver-survival-backup-{attempt}"),
+            chart_name: "mcserver-survival".to_string(),
+            job_name: "backup".to_string(),
+            attempt,
+            succeeded,
+        };
+        RunReport {
+            run_id: "20270115-040000".to_string(),
+            routine: "daily".to_string(),
+            started_at: at(0),
+            finished_at: at(754),
+            succeeded,
+            tasks: vec![
+                TaskReport {
+                    name: "shutdown_mcserver/survival".to_string(),
+                    status: TaskStatus::Succeeded,
+                    started_at: Some(at(5)),
+                    finished_at: Some(at(20)),
+                    duration_seconds: Some(15.0),
+                },
+                TaskReport {
+                    name: "relaunch_mcserver/survival".to_string(),
+                    status: TaskStatus::NotStarted,
+                    started_at: None,
+                    finished_at: None,
+                    duration_seconds: None,
+                },
+            ],
+            errors: vec![],
+            argocd_applications: vec![
+                ArgoCdReport {
+                    application: "mcserver-survival".to_string(),
+                    restored: false,
+                    held_after_canary_failure: false,
+                },
+                ArgoCdReport {
+                    application: "mcproxy".to_string(),
+                    restored: true,
+                    held_after_canary_failure: false,
+                },
+            ],
+            jobs: vec![job(2, true), job(1, false)],
+            downtime: vec![DowntimeReport {
+                server: "survival".to_string(),
+                chart_name: "mcserver-survival".to_string(),
+                down_at: at(5),
+                up_at: None,
+                downtime_seconds: 749.0,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let previous = last_successes(&render(&report(true), &BTreeMap::new()));
+        assert_eq!(previous["daily"], "1800000754");
+
+        let metrics = render(&report(false), &previous);
+        let lines: Vec<&str> = metrics.lines().collect();
+        for expected in [
+            r#"man10routine_last_run_success{routine="daily"} 0"#,
+            r#"man10routine_last_success_timestamp{routine="daily"} 1800000754"#,
+            r#"man10routine_run_duration_seconds{routine="daily"} 754"#,
+            r#"man10routine_task_duration_seconds{routine="daily",task="shutdown_mcserver/survival"} 15"#,
+            r#"man10routine_task_success{routine="daily",task="relaunch_mcserver/survival"} 0"#,
+            r#"man10routine_server_downtime_seconds{routine="daily",server="survival"} 749"#,
+            r#"man10routine_job_success{routine="daily",chart="mcserver-survival",job="backup"} 1"#,
+            r#"man10routine_job_attempts{routine="daily",chart="mcserver-survival",job="backup"} 2"#,
+            r#"man10routine_argocd_restore_failures{routine="daily"} 1"#,
+        ] {
+            assert!(
+                lines.contains(&expected),
+                "{expected} is missing:\n{metrics}"
+            );
+        }
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|l| l.starts_with("# TYPE man10routine_task_success "))
+                .count(),
+            1
+        );
+        assert!(
+            !render(&report(false), &BTreeMap::new()).contains(LAST_SUCCESS_METRIC),
+            "a failed run does not report a success"
+        );
+        let exposition = format!(
+            "# TYPE {LAST_SUCCESS_METRIC} gauge\n\
+             {LAST_SUCCESS_METRIC}{{instance=\"\",job=\"man10routine\",routine=\"rolling\"}} 1.7e+09\n\
+             {LAST_SUCCESS_METRIC}{{instance=\"\",job=\"man10routine\",routine=\"daily\"}} 1.800000754e+09\n"
+        );
+        assert_eq!(
+            pushed_last_success(&exposition, "man10routine", "daily").as_deref(),
+            Some("1.800000754e+09")
+        );
+        assert_eq!(pushed_last_success(&exposition, "other", "daily"), None);
+        assert_eq!(
+            labels(&[("task", "a\"b\\c")]),
+            r#"{task="a\"b\\c"}"#.to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_metrics() {
+        let dir = std::env::temp_dir().join(format!("man10routine-metrics-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let textfile = dir.join("man10routine.prom");
+        let (url, mut requests) = stand_in(200).await;
+        let config = MetricsConfig {
+            textfile: Some(textfile.clone()),
+            pushgateway: Some(url),
+            job: "man10routine".to_string(),
+        };
+
+        export_metrics(&config, &report(true)).await.unwrap();
+        export_metrics(&config, &report(false)).await.unwrap();
+
+        let written = tokio::fs::read_to_string(&textfile).await.unwrap();
+        assert!(written.contains(r#"man10routine_last_run_success{routine="daily"} 0"#));
+        assert!(
+            written.contains(r#"man10routine_last_success_timestamp{routine="daily"} 1800000754"#)
+        );
+
+        let read = requests.recv().await.unwrap();
+        assert!(read.head.starts_with("GET /hook/metrics HTTP/1.1"));
+        let pushed = requests.recv().await.unwrap();
+        assert!(
+            pushed
+                .head
+                .starts_with("PUT /hook/metrics/job/man10routine/routine/daily HTTP/1.1")
+        );
+        assert!(pushed.body.contains(LAST_SUCCESS_METRIC));
+        requests.recv().await.unwrap();
+        let pushed = requests.recv().await.unwrap();
+        assert!(!pushed.body.contains(LAST_SUCCESS_METRIC));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -0,0 +1,50 @@
+use futures::future::BoxFuture;
+use hyper::Uri;
+use serde_json::json;
+
+use crate::config::notifications::NotificationKind;
+
+use super::{HttpClient, Notification, Notifier, NotifyError, post_json};
+
+const COLOR_INFO: u32 = 0x3498db;
+const COLOR_SUCCESS: u32 = 0x2ecc71;
+const COLOR_FAILURE: u32 = 0xe74c3c;
+const COLOR_ALERT: u32 = 0xe67e22;
+
+/// Posts notifications as embeds to a Discord webhook
+pub(super) struct DiscordNotifier {
+    http: HttpClient,
+    url: Uri,
+}
+
+impl DiscordNotifier {
+    pub(super) fn new(http: HttpClient, url: Uri) -> DiscordNotifier {
+        DiscordNotifier { http, url }
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn send<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), NotifyError>> {
+        let color = match (notification.kind, notification.succeeded) {
+            (NotificationKind::Start, _) => COLOR_INFO,
+            (NotificationKind::Summary, Some(true)) => COLOR_SUCCESS,
+            (NotificationKind::Summary, _) => COLOR_FAILURE,
+            (NotificationKind::Alert, _) => COLOR_ALERT,
+        };
+        let payload = json!({
+            "username": "man10routine",
+            "embeds": [{
+                "title": notification.title,
+                "description": notification.text,
+                "color": color,
+                "footer": { "text": format!("run {}", notification.run_id) },
+            }],
+        });
+        Box::pin(
+            async move { post_json(&self.http, &self.url, &Default::default(), &payload).await },
+        )
+    }
+}
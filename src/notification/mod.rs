@@ -0,0 +1,249 @@
+mod discord;
+mod slack;
+mod webhook;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::{BoxFuture, join_all};
+use hyper::{Method, Uri};
+use serde::Serialize;
+use thiserror::Error;
+use tracing::{debug, instrument, warn};
+
+use crate::config::notifications::{NotificationKind, NotificationsConfig, SinkKind};
+use crate::http::{HttpClient, HttpError, http_client, send};
+
+use self::discord::DiscordNotifier;
+use self::slack::SlackNotifier;
+use self::webhook::WebhookNotifier;
+
+/// Time allowed for a single sink to accept a message
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum NotifyError {
+    #[error("{0}")]
+    Http(#[from] HttpError),
+
+    #[error("Sink did not respond within {0:?}")]
+    Timeout(Duration),
+}
+
+/// Message about a run
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct Notification {
+    pub(crate) kind: NotificationKind,
+    pub(crate) run_id: String,
+    pub(crate) title: String,
+    pub(crate) text: String,
+
+    /// Whether the run succeeded, only set on `summary`
+    pub(crate) succeeded: Option<bool>,
+}
+
+/// Destination to which notifications are posted
+pub(crate) trait Notifier: Send + Sync {
+    fn send<'a>(&'a self, notification: &'a Notification)
+    -> BoxFuture<'a, Result<(), NotifyError>>;
+}
+
+struct Sink {
+    url: String,
+    events: Vec<NotificationKind>,
+    notifier: Box<dyn Notifier>,
+}
+
+/// Every configured sink, shared by the tasks of a run
+#[derive(Clone)]
+pub(crate) struct Notifications {
+    sinks: Arc<Vec<Sink>>,
+}
+
+impl Notifications {
+    pub(crate) fn new(config: &NotificationsConfig) -> Notifications {
+        if config.sinks.is_empty() {
+            return Notifications {
+                sinks: Arc::new(Vec::new()),
+            };
+        }
+
+        let http = http_client();
+        let sinks = config
+            .sinks
+            .iter()
+            .map(|sink| {
+                let url: Uri = sink
+                    .kind
+                    .url()
+                    .parse()
+                    .expect("notification URLs have been validated while parsing the config");
+                let notifier: Box<dyn Notifier> = match &sink.kind {
+                    SinkKind::Discord { .. } => Box::new(DiscordNotifier::new(http.clone(), url)),
+                    SinkKind::Slack { .. } => Box::new(SlackNotifier::new(http.clone(), url)),
+                    SinkKind::Webhook { headers, .. } => {
+                        Box::new(WebhookNotifier::new(http.clone(), url, headers.clone()))
+                    }
+                };
+                Sink {
+                    url: sink.kind.url().to_string(),
+                    events: sink.events.clone(),
+                    notifier,
+                }
+            })
+            .collect();
+        Notifications {
+            sinks: Arc::new(sinks),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Posts the notification to every sink subscribed to its kind, logging failures.
+    #[instrument("notify", skip(self, notification), fields(kind = ?notification.kind))]
+    pub(crate) async fn notify(&self, notification: Notification) {
+        let sends = self
+            .sinks
+            .iter()
+            .filter(|sink| sink.events.contains(&notification.kind))
+            .map(|sink| async {
+                let result = tokio::time::timeout(SEND_TIMEOUT, sink.notifier.send(&notification))
+                    .await
+                    .unwrap_or(Err(NotifyError::Timeout(SEND_TIMEOUT)));
+                match result {
+                    Ok(()) => debug!("Notification posted to {}.", sink.url),
+                    Err(e) => warn!("Failed to post notification to {}: {}", sink.url, e),
+                }
+            });
+        join_all(sends).await;
+    }
+
+    /// Posts an alert about the run.
+    pub(crate) async fn alert(&self, run_id: &str, title: String, text: String) {
+        self.notify(Notification {
+            kind: NotificationKind::Alert,
+            run_id: run_id.to_string(),
+            title,
+            text,
+            succeeded: None,
+        })
+        .await;
+    }
+}
+
+/// Posts `body` as JSON and fails unless the response status is 2xx.
+async fn post_json(
+    http: &HttpClient,
+    url: &Uri,
+    headers: &BTreeMap<String, String>,
+    body: &impl Serialize,
+) -> Result<(), NotifyError> {
+    let body = serde_json::to_vec(body).expect("notification payloads can be serialized");
+    send(http, Method::POST, url, headers, "application/json", body).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::notifications::SinkConfig;
+    use crate::http::tests::stand_in;
+
+    fn sink(kind: SinkKind, events: Vec<NotificationKind>) -> SinkConfig {
+        SinkConfig { kind, events }
+    }
+
+    fn summary() -> Notification {
+        Notification {
+            kind: NotificationKind::Summary,
+            run_id: "20270115-040000".to_string(),
+            title: "Daily routine failed".to_string(),
+            text: "Failed tasks: shutdown_mcserver/survival".to_string(),
+            succeeded: Some(false),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sinks_post_to_stand_in() {
+        let (discord_url, mut discord) = stand_in(204).await;
+        let (slack_url, mut slack) = stand_in(200).await;
+        let (webhook_url, mut webhook) = stand_in(200).await;
+        let (alerts_url, mut alerts) = stand_in(200).await;
+        let notifications = Notifications::new(&NotificationsConfig {
+            sinks: vec![
+                sink(
+                    SinkKind::Discord { url: discord_url },
+                    vec![NotificationKind::Summary],
+                ),
+                sink(
+                    SinkKind::Slack { url: slack_url },
+                    vec![NotificationKind::Summary],
+                ),
+                sink(
+                    SinkKind::Webhook {
+                        url: webhook_url,
+                        headers: BTreeMap::from([(
+                            "Authorization".to_string(),
+                            "Bearer secret".to_string(),
+                        )]),
+                    },
+                    vec![NotificationKind::Summary],
+                ),
+                sink(
+                    SinkKind::Webhook {
+                        url: alerts_url,
+                        headers: BTreeMap::new(),
+                    },
+                    vec![NotificationKind::Alert],
+                ),
+            ],
+            ..Default::default()
+        });
+
+        notifications.notify(summary()).await;
+
+        let discord = discord.recv().await.unwrap();
+        assert!(discord.head.starts_with("POST /hook HTTP/1.1"));
+        assert_eq!(discord.json()["embeds"][0]["title"], "Daily routine failed");
+        assert_eq!(discord.json()["embeds"][0]["color"], 0xe74c3c);
+
+        let slack = slack.recv().await.unwrap();
+        assert!(
+            slack.json()["text"]
+                .as_str()
+                .unwrap()
+                .starts_with("*Daily routine failed*")
+        );
+
+        let webhook = webhook.recv().await.unwrap();
+        assert!(
+            webhook
+                .head
+                .to_lowercase()
+                .contains("authorization: bearer secret")
+        );
+        assert_eq!(webhook.json()["kind"], "summary");
+        assert_eq!(webhook.json()["succeeded"], false);
+
+        assert!(alerts.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_post_json_error_status() {
+        let (url, _requests) = stand_in(500).await;
+        let result = post_json(
+            &http_client(),
+            &url.parse().unwrap(),
+            &BTreeMap::new(),
+            &summary(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(NotifyError::Http(HttpError::Status { status: 500, .. }))
+        ));
+    }
+}
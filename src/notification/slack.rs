@@ -0,0 +1,34 @@
+use futures::future::BoxFuture;
+use hyper::Uri;
+use serde_json::json;
+
+use super::{HttpClient, Notification, Notifier, NotifyError, post_json};
+
+/// Posts notifications as text to a Slack incoming webhook
+pub(super) struct SlackNotifier {
+    http: HttpClient,
+    url: Uri,
+}
+
+impl SlackNotifier {
+    pub(super) fn new(http: HttpClient, url: Uri) -> SlackNotifier {
+        SlackNotifier { http, url }
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn send<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), NotifyError>> {
+        let payload = json!({
+            "text": format!(
+                "*{}*\n{}\n_run {}_",
+                notification.title, notification.text, notification.run_id
+            ),
+        });
+        Box::pin(
+            async move { post_json(&self.http, &self.url, &Default::default(), &payload).await },
+        )
+    }
+}
@@ -0,0 +1,37 @@
+use std::collections::BTreeMap;
+
+use futures::future::BoxFuture;
+use hyper::Uri;
+
+use super::{HttpClient, Notification, Notifier, NotifyError, post_json};
+
+/// Posts the notification itself as JSON to any endpoint
+pub(super) struct WebhookNotifier {
+    http: HttpClient,
+    url: Uri,
+    headers: BTreeMap<String, String>,
+}
+
+impl WebhookNotifier {
+    pub(super) fn new(
+        http: HttpClient,
+        url: Uri,
+        headers: BTreeMap<String, String>,
+    ) -> WebhookNotifier {
+        WebhookNotifier { http, url, headers }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn send<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), NotifyError>> {
+        Box::pin(post_json(
+            &self.http,
+            &self.url,
+            &self.headers,
+            notification,
+        ))
+    }
+}
@@ -0,0 +1,715 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, IsTerminal, Stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::crossterm::cursor::{Hide, Show};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Cell, Paragraph, Row, Table};
+use ratatui::{Frame, Terminal};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::JoinHandle;
+use tokio::time::{self, MissedTickBehavior};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Metadata, Subscriber, warn};
+use tracing_subscriber::Layer;
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::scheduler::TaskEvent;
+
+/// Interval between two redraws when no task changes state
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Live view of the tasks of a run, drawn on the terminal in place of the logs.
+#[derive(Clone)]
+pub struct Progress {
+    board: Arc<Mutex<Board>>,
+    screen: Arc<Screen>,
+}
+
+impl Progress {
+    /// Returns `None` when stdout is not a terminal, in which case plain logs should be kept.
+    pub fn new() -> Option<Progress> {
+        if !io::stdout().is_terminal() {
+            return None;
+        }
+        Some(Progress {
+            board: Arc::new(Mutex::new(Board::default())),
+            screen: Arc::new(Screen::default()),
+        })
+    }
+
+    /// Layer keeping the latest log line of each task, taken from its `flight_task` span
+    pub fn layer<S>(&self) -> impl Layer<S> + use<S>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        ProgressLayer {
+            board: self.board.clone(),
+            screen: self.screen.clone(),
+        }
+        .with_filter(filter_fn(|metadata: &Metadata<'_>| {
+            if metadata.target().starts_with(env!("CARGO_CRATE_NAME")) {
+                metadata.is_span() || *metadata.level() <= Level::INFO
+            } else {
+                *metadata.level() <= Level::WARN
+            }
+        }))
+    }
+
+    /// Writer for the log lines to stdout. While the view is on screen, warnings and errors are
+    /// held until it is left and the other lines are discarded.
+    pub fn writer(&self) -> ProgressWriter {
+        ProgressWriter {
+            screen: self.screen.clone(),
+            stream: Stream::Stdout,
+        }
+    }
+
+    /// Writer to stderr, whose output is held until the view is left
+    pub fn error_writer(&self) -> ProgressWriter {
+        ProgressWriter {
+            screen: self.screen.clone(),
+            stream: Stream::Stderr,
+        }
+    }
+
+    /// Shows the task as skipped instead of succeeded once it finishes.
+    pub(crate) fn skip(&self, task_name: &str) {
+        self.board().skip(task_name);
+    }
+
+    /// Puts the view of the `stages` of the DAG on screen until [`ProgressDisplay::finish`].
+    pub(crate) fn start(&self, title: &str, stages: Vec<Vec<String>>) -> ProgressDisplay {
+        *self.board() = Board::new(title, stages);
+
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+        let progress = self.clone();
+        let handle = tokio::spawn(async move {
+            let mut terminal = match enter_screen() {
+                Ok(terminal) => terminal,
+                Err(e) => {
+                    warn!("Failed to draw the progress, falling back to logs: {e}");
+                    return;
+                }
+            };
+            progress.screen.active.store(true, Ordering::SeqCst);
+
+            let mut redraw = time::interval(REDRAW_INTERVAL);
+            redraw.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let result = loop {
+                tokio::select! {
+                    event = events_rx.recv() => match event {
+                        Some(event) => progress.board().apply(event, Instant::now()),
+                        None => break Ok(()),
+                    },
+                    _ = redraw.tick() => {}
+                }
+                let drawn = terminal.draw(|frame| render(&progress.board(), Instant::now(), frame));
+                if let Err(e) = drawn {
+                    break Err(e);
+                }
+            };
+
+            let left = leave_screen(&mut terminal);
+            progress.screen.release();
+            if let Err(e) = result.and(left) {
+                warn!("Failed to draw the progress: {e}");
+            }
+        });
+
+        ProgressDisplay {
+            events: events_tx,
+            handle,
+        }
+    }
+
+    fn board(&self) -> MutexGuard<'_, Board> {
+        self.board.lock().expect("progress board lock poisoned")
+    }
+}
+
+/// View put on screen by [`Progress::start`]
+pub(crate) struct ProgressDisplay {
+    events: UnboundedSender<TaskEvent>,
+    handle: JoinHandle<()>,
+}
+
+impl ProgressDisplay {
+    /// Sender to give to [`crate::scheduler::Scheduler::with_events`]
+    pub(crate) fn events(&self) -> UnboundedSender<TaskEvent> {
+        self.events.clone()
+    }
+
+    /// Restores the terminal once the scheduler, holding the other senders, has been dropped.
+    pub(crate) async fn finish(self) {
+        drop(self.events);
+        if let Err(e) = self.handle.await {
+            warn!("Progress display stopped unexpectedly: {e}");
+        }
+    }
+}
+
+fn enter_screen() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, Hide)?;
+    Terminal::new(CrosstermBackend::new(stdout))
+}
+
+fn leave_screen<B: Backend + io::Write>(terminal: &mut Terminal<B>) -> io::Result<()> {
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, Show)
+}
+
+/// Terminal shared by the view and the writers of the logs
+#[derive(Default)]
+struct Screen {
+    /// Whether the view is on screen
+    active: AtomicBool,
+
+    /// Output held while the view is on screen
+    held: Mutex<Vec<(Stream, Vec<u8>)>>,
+}
+
+impl Screen {
+    fn held(&self) -> MutexGuard<'_, Vec<(Stream, Vec<u8>)>> {
+        self.held.lock().expect("held output lock poisoned")
+    }
+
+    /// Marks the view as left and prints the held output.
+    fn release(&self) {
+        let mut held = self.held();
+        self.active.store(false, Ordering::SeqCst);
+        for (stream, output) in held.drain(..) {
+            stream.write(&output);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn write(self, output: &[u8]) {
+        let _ = match self {
+            Stream::Stdout => io::stdout().write_all(output),
+            Stream::Stderr => io::stderr().write_all(output),
+        };
+    }
+}
+
+/// [`MakeWriter`] returned by [`Progress::writer`] and [`Progress::error_writer`]
+pub struct ProgressWriter {
+    screen: Arc<Screen>,
+    stream: Stream,
+}
+
+impl<'a> MakeWriter<'a> for ProgressWriter {
+    type Writer = ProgressOutput;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        ProgressOutput {
+            screen: self.screen.clone(),
+            stream: self.stream,
+            discard: false,
+            output: Vec::new(),
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        let mut output = self.make_writer();
+        output.discard = *meta.level() > Level::WARN;
+        output
+    }
+}
+
+/// Output of one log line, written, held or discarded once complete
+pub struct ProgressOutput {
+    screen: Arc<Screen>,
+    stream: Stream,
+
+    /// Whether the output is dropped instead of held while the view is on screen
+    discard: bool,
+    output: Vec<u8>,
+}
+
+impl Write for ProgressOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for ProgressOutput {
+    fn drop(&mut self) {
+        // `active` is read under the lock so that nothing is held after `Screen::release` has
+        // printed the held output
+        let mut held = self.screen.held();
+        if !self.screen.active.load(Ordering::SeqCst) {
+            drop(held);
+            self.stream.write(&self.output);
+        } else if !self.discard {
+            held.push((self.stream, std::mem::take(&mut self.output)));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TaskState {
+    Pending,
+    Running,
+    Succeeded,
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone)]
+struct TaskProgress {
+    state: TaskState,
+    started_at: Option<Instant>,
+    finished_at: Option<Instant>,
+    last_line: Option<LogLine>,
+}
+
+#[derive(Debug, Clone)]
+struct LogLine {
+    level: Level,
+    message: String,
+}
+
+#[derive(Debug, Default)]
+struct Board {
+    title: String,
+    started_at: Option<Instant>,
+    stages: Vec<Vec<String>>,
+    tasks: HashMap<String, TaskProgress>,
+
+    /// Latest log line outside of any task
+    last_line: Option<LogLine>,
+}
+
+impl Board {
+    fn new(title: &str, stages: Vec<Vec<String>>) -> Board {
+        let tasks = stages
+            .iter()
+            .flatten()
+            .map(|name| {
+                let task = TaskProgress {
+                    state: TaskState::Pending,
+                    started_at: None,
+                    finished_at: None,
+                    last_line: None,
+                };
+                (name.clone(), task)
+            })
+            .collect();
+        Board {
+            title: title.to_string(),
+            started_at: Some(Instant::now()),
+            stages,
+            tasks,
+            last_line: None,
+        }
+    }
+
+    fn apply(&mut self, event: TaskEvent, now: Instant) {
+        match event {
+            TaskEvent::Started(name) => {
+                if let Some(task) = self.tasks.get_mut(&name) {
+                    task.state = TaskState::Running;
+                    task.started_at = Some(now);
+                }
+            }
+            TaskEvent::Finished { name, succeeded } => {
+                if let Some(task) = self.tasks.get_mut(&name) {
+                    task.state = match (succeeded, task.state) {
+                        (true, TaskState::Skipped) => TaskState::Skipped,
+                        (true, _) => TaskState::Succeeded,
+                        (false, _) => TaskState::Failed,
+                    };
+                    task.finished_at = Some(now);
+                }
+            }
+        }
+    }
+
+    /// Marks the running task as skipped, which the scheduler reports as succeeded.
+    fn skip(&mut self, name: &str) {
+        if let Some(task) = self.tasks.get_mut(name) {
+            task.state = TaskState::Skipped;
+        }
+    }
+
+    fn log(&mut self, task_name: Option<&str>, line: LogLine) {
+        match task_name.and_then(|name| self.tasks.get_mut(name)) {
+            Some(task) => task.last_line = Some(line),
+            None => self.last_line = Some(line),
+        }
+    }
+
+    fn count(&self, state: TaskState) -> usize {
+        self.tasks.values().filter(|t| t.state == state).count()
+    }
+}
+
+/// Header with the elapsed time and task counts, the tasks by stage, and the latest log line
+/// outside of any task. Stages of several tasks which are all pending or all succeeded are
+/// collapsed into a single row to fit the screen.
+fn render(board: &Board, now: Instant, frame: &mut Frame) {
+    let [header_area, table_area, footer_area] = Layout::vertical([
+        Constraint::Length(2),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let elapsed = board
+        .started_at
+        .map(|started_at| now - started_at)
+        .unwrap_or_default();
+    let mut header = Line::from(vec![
+        board.title.clone().bold(),
+        format!("  {}  ", format_elapsed(elapsed)).into(),
+        format!("{} running", board.count(TaskState::Running)).yellow(),
+        ", ".into(),
+        format!("{} succeeded", board.count(TaskState::Succeeded)).green(),
+        ", ".into(),
+        format!("{} failed", board.count(TaskState::Failed)).red(),
+        format!(", {} pending", board.count(TaskState::Pending)).into(),
+    ]);
+    let skipped = board.count(TaskState::Skipped);
+    if skipped > 0 {
+        header.push_span(format!(", {skipped} skipped").dark_gray());
+    }
+    frame.render_widget(Paragraph::new(header), header_area);
+
+    let mut rows = Vec::new();
+    for (index, stage) in board.stages.iter().enumerate() {
+        let tasks: Vec<(&String, &TaskProgress)> = stage
+            .iter()
+            .filter_map(|name| board.tasks.get(name).map(|task| (name, task)))
+            .collect();
+        let stage_label = format!("{}", index + 1);
+
+        let collapsed = [TaskState::Pending, TaskState::Succeeded, TaskState::Skipped]
+            .into_iter()
+            .find(|state| tasks.len() > 1 && tasks.iter().all(|(_, t)| t.state == *state));
+        if let Some(state) = collapsed {
+            rows.push(Row::new(vec![
+                Cell::from(stage_label),
+                Cell::from(format!("{} tasks", tasks.len())),
+                state_cell(state),
+            ]));
+            continue;
+        }
+
+        for (position, (name, task)) in tasks.into_iter().enumerate() {
+            let elapsed = task
+                .started_at
+                .map(|started_at| task.finished_at.unwrap_or(now) - started_at)
+                .map(format_elapsed)
+                .unwrap_or_default();
+            rows.push(Row::new(vec![
+                Cell::from(if position == 0 {
+                    stage_label.clone()
+                } else {
+                    String::new()
+                }),
+                Cell::from(name.as_str()),
+                state_cell(task.state),
+                Cell::from(elapsed),
+                log_cell(task.last_line.as_ref()),
+            ]));
+        }
+    }
+
+    let name_width = board
+        .tasks
+        .keys()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(10);
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(5),
+            Constraint::Length(u16::try_from(name_width).unwrap_or(u16::MAX)),
+            Constraint::Length(9),
+            Constraint::Length(8),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(Row::new(vec!["Stage", "Task", "State", "Elapsed", "Latest log"]).bold());
+    frame.render_widget(table, table_area);
+
+    frame.render_widget(
+        Paragraph::new(Line::from(log_cell_spans(board.last_line.as_ref()))),
+        footer_area,
+    );
+}
+
+fn state_cell(state: TaskState) -> Cell<'static> {
+    match state {
+        TaskState::Pending => Cell::from("pending").dark_gray(),
+        TaskState::Running => Cell::from("running").yellow(),
+        TaskState::Succeeded => Cell::from("succeeded").green(),
+        TaskState::Skipped => Cell::from("skipped").dark_gray(),
+        TaskState::Failed => Cell::from("failed").red(),
+    }
+}
+
+fn log_cell(line: Option<&LogLine>) -> Cell<'static> {
+    Cell::from(Line::from(log_cell_spans(line)))
+}
+
+fn log_cell_spans(line: Option<&LogLine>) -> Vec<Span<'static>> {
+    let Some(line) = line else {
+        return Vec::new();
+    };
+    let style = match line.level {
+        Level::ERROR => Style::default().fg(Color::Red),
+        Level::WARN => Style::default().fg(Color::Yellow),
+        _ => Style::default(),
+    };
+    vec![Span::styled(line.message.clone(), style)]
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// Name of the task run within a `flight_task` span, kept in the span's extensions
+struct FlightTask(String);
+
+struct ProgressLayer {
+    board: Arc<Mutex<Board>>,
+    screen: Arc<Screen>,
+}
+
+impl<S> Layer<S> for ProgressLayer
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != "flight_task" {
+            return;
+        }
+        let mut visitor = FieldVisitor::new("task_name");
+        attrs.record(&mut visitor);
+        if let (Some(task_name), Some(span)) = (visitor.value, ctx.span(id)) {
+            span.extensions_mut().insert(FlightTask(task_name));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !self.screen.active.load(Ordering::SeqCst) {
+            return;
+        }
+        let mut visitor = FieldVisitor::new("message");
+        event.record(&mut visitor);
+        let Some(message) = visitor.value else {
+            return;
+        };
+        // Only the first line is kept so that each task stays on a single row
+        let message = message.lines().next().unwrap_or_default().to_string();
+
+        let task_name = ctx.event_scope(event).and_then(|scope| {
+            scope
+                .into_iter()
+                .find_map(|span| span.extensions().get::<FlightTask>().map(|t| t.0.clone()))
+        });
+        let line = LogLine {
+            level: *event.metadata().level(),
+            message,
+        };
+        self.board
+            .lock()
+            .expect("progress board lock poisoned")
+            .log(task_name.as_deref(), line);
+    }
+}
+
+/// Visitor taking the value of a single field
+struct FieldVisitor {
+    name: &'static str,
+    value: Option<String>,
+}
+
+impl FieldVisitor {
+    fn new(name: &'static str) -> FieldVisitor {
+        FieldVisitor { name, value: None }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == self.name {
+            self.value = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == self.name {
+            self.value = Some(format!("{value:?}"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::TestBackend;
+    use tracing::{info, trace_span, warn};
+    use tracing_subscriber::prelude::*;
+
+    use super::*;
+
+    fn screen(terminal: &Terminal<TestBackend>) -> Vec<String> {
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .map(|row| row.trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_render_tasks_by_stage_with_latest_log() {
+        let progress = Progress {
+            board: Arc::new(Mutex::new(Board::default())),
+            screen: Arc::new(Screen::default()),
+        };
+        progress.screen.active.store(true, Ordering::SeqCst);
+        let stages = vec![
+            vec![
+                "argocd_teardown".to_string(),
+                "execute_job/global/render-map".to_string(),
+            ],
+            vec![
+                "shutdown_mcserver/lobby".to_string(),
+                "shutdown_mcserver/survival".to_string(),
+            ],
+            vec![
+                "relaunch_mcserver/lobby".to_string(),
+                "relaunch_mcserver/survival".to_string(),
+            ],
+        ];
+        *progress.board() = Board::new("Daily routine", stages);
+
+        let start = Instant::now();
+        {
+            let mut board = progress.board();
+            board.apply(TaskEvent::Started("argocd_teardown".to_string()), start);
+            board.apply(
+                TaskEvent::Finished {
+                    name: "argocd_teardown".to_string(),
+                    succeeded: true,
+                },
+                start + Duration::from_secs(3),
+            );
+            for name in ["shutdown_mcserver/lobby", "shutdown_mcserver/survival"] {
+                board.apply(TaskEvent::Started(name.to_string()), start);
+            }
+        }
+        progress.skip("execute_job/global/render-map");
+        progress.board().apply(
+            TaskEvent::Finished {
+                name: "execute_job/global/render-map".to_string(),
+                succeeded: true,
+            },
+            start,
+        );
+
+        let subscriber = tracing_subscriber::registry().with(progress.layer());
+        tracing::subscriber::with_default(subscriber, || {
+            info!("Starting daily routine...");
+            let _task = trace_span!("flight_task", task_name = "shutdown_mcserver/lobby").entered();
+            warn!("Pod is still terminating.\nretrying");
+        });
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 10)).unwrap();
+        terminal
+            .draw(|frame| render(&progress.board(), start + Duration::from_secs(65), frame))
+            .unwrap();
+        let screen = screen(&terminal);
+
+        assert_eq!(
+            screen[0],
+            "Daily routine  1:05  2 running, 1 succeeded, 0 failed, 2 pending, 1 skipped"
+        );
+        let row = |task: &str| screen.iter().find(|row| row.contains(task)).unwrap();
+        assert!(row("argocd_teardown").contains("succeeded 0:03"));
+        assert!(row("execute_job/global/render-map").contains("skipped"));
+        assert!(
+            row("shutdown_mcserver/lobby")
+                .ends_with("running   1:05     Pod is still terminating.")
+        );
+        assert!(row("shutdown_mcserver/survival").ends_with("running   1:05"));
+        assert!(row("2 tasks").contains("pending"));
+        assert_eq!(screen[9], "Starting daily routine...");
+    }
+
+    #[test]
+    fn test_hold_warnings_while_on_screen() {
+        let progress = Progress {
+            board: Arc::new(Mutex::new(Board::default())),
+            screen: Arc::new(Screen::default()),
+        };
+        progress.screen.active.store(true, Ordering::SeqCst);
+
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(progress.writer()),
+        );
+        tracing::subscriber::with_default(subscriber, || {
+            info!("Starting daily routine...");
+            warn!("Pod is still terminating.");
+        });
+        progress
+            .error_writer()
+            .make_writer()
+            .write_all(b"span trace")
+            .unwrap();
+
+        {
+            let held = progress.screen.held();
+            assert_eq!(held.len(), 2);
+            assert_eq!(held[0].0, Stream::Stdout);
+            assert!(String::from_utf8_lossy(&held[0].1).contains("Pod is still terminating."));
+            assert_eq!(held[1], (Stream::Stderr, b"span trace".to_vec()));
+        }
+
+        progress.screen.release();
+        assert!(progress.screen.held().is_empty());
+        assert!(!progress.screen.active.load(Ordering::SeqCst));
+    }
+}
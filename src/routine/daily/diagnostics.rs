@@ -0,0 +1,60 @@
+use tracing::{error, instrument};
+
+use crate::kubernetes_objects::diagnostics::{
+    Diagnostics, collect_job_diagnostics, collect_statefulset_diagnostics,
+};
+
+use super::DailyRoutineContext;
+use super::error::DailyRoutineError;
+
+impl DailyRoutineContext {
+    /// Attaches diagnostics of the StatefulSet and its pod to the error, if any.
+    #[instrument("diagnose_statefulset", skip(self, result))]
+    pub(super) async fn diagnose_statefulset<T>(
+        &self,
+        result: Result<T, DailyRoutineError>,
+        statefulset_name: &str,
+    ) -> Result<T, DailyRoutineError> {
+        let Err(e) = result else {
+            return result;
+        };
+        let diagnostics = collect_statefulset_diagnostics(
+            self.client.clone(),
+            &self.config.namespace,
+            statefulset_name,
+            &self.config.diagnostics,
+        )
+        .await;
+        Err(attach(e, diagnostics, "StatefulSet", statefulset_name))
+    }
+
+    /// Attaches diagnostics of the Job and its pods to the error, if any.
+    #[instrument("diagnose_job", skip(self, result))]
+    pub(super) async fn diagnose_job<T>(
+        &self,
+        result: Result<T, DailyRoutineError>,
+        job_name: &str,
+    ) -> Result<T, DailyRoutineError> {
+        let Err(e) = result else {
+            return result;
+        };
+        let diagnostics = collect_job_diagnostics(
+            self.client.clone(),
+            &self.config.namespace,
+            job_name,
+            &self.config.diagnostics,
+        )
+        .await;
+        Err(attach(e, diagnostics, "Job", job_name))
+    }
+}
+
+fn attach(
+    e: DailyRoutineError,
+    diagnostics: Diagnostics,
+    kind: &str,
+    name: &str,
+) -> DailyRoutineError {
+    error!("Diagnostics of {kind} '{name}':\n{diagnostics}");
+    DailyRoutineError::Diagnosed(Box::new(e), Box::new(diagnostics))
+}
@@ -1,14 +1,18 @@
-use k8s_openapi::api::batch::v1::JobStatus;
 use thiserror::Error;
 use tracing_error::{ExtractSpanTrace, SpanTrace};
 
 use crate::error::SpannedErr;
 use crate::kubernetes_objects::argocd::ArgoCdError;
-use crate::kubernetes_objects::job::WaitJobFinishedError;
+use crate::kubernetes_objects::diagnostics::Diagnostics;
+use crate::kubernetes_objects::job::{JobFailure, WaitJobFinishedError};
 use crate::kubernetes_objects::minecraft_chart::MinecraftChartError;
+use crate::kubernetes_objects::pod::PodExecError;
 use crate::kubernetes_objects::statefulset::StatefulSetScaleError;
 use crate::scheduler::InvalidDagError;
 
+use super::phase_execute_job::PodJobError;
+use super::phase_verify_canary::CanaryCheckError;
+
 #[derive(Error, Debug)]
 pub enum DailyRoutineError {
     #[error("ArgoCD error: {0}")]
@@ -23,11 +27,20 @@ pub enum DailyRoutineError {
     #[error("Minecraft Server {0} cannot be relaunch: {1}")]
     RelaunchMinecraftServer(String, StatefulSetScaleError),
 
+    #[error("Players on Minecraft Server {0} cannot be moved by proxy {1}: {2}")]
+    MovePlayers(String, String, SpannedErr<PodExecError>),
+
+    #[error("Canary Minecraft Server {0} failed verification: {1}")]
+    CanaryVerification(String, SpannedErr<CanaryCheckError>),
+
     #[error("Job {0} cannot be finished: {1}")]
     WaitJobFinished(String, SpannedErr<WaitJobFinishedError>),
 
-    #[error("Custom job {0} has failed with status {1:?}")]
-    CustomJobHasFailure(String, JobStatus, SpanTrace),
+    #[error("Custom job {0} has failed with {1}")]
+    CustomJobHasFailure(String, JobFailure, SpanTrace),
+
+    #[error("Custom job {0} has failed in its pod: {1}")]
+    PodJob(String, SpannedErr<PodJobError>),
 
     #[error("Kubernetes client error: {0}")]
     KubeClient(#[from] SpannedErr<kube::Error>),
@@ -37,6 +50,27 @@ pub enum DailyRoutineError {
 
     #[error("Invalid task DAG: {0}")]
     InvalidTaskDag(#[from] SpannedErr<InvalidDagError>),
+
+    #[error("{0}")]
+    Diagnosed(Box<DailyRoutineError>, Box<Diagnostics>),
+}
+
+impl DailyRoutineError {
+    /// Diagnostics collected from the objects involved in the failure
+    pub fn diagnostics(&self) -> Option<&Diagnostics> {
+        match self {
+            DailyRoutineError::Diagnosed(_, diagnostics) => Some(diagnostics),
+            _ => None,
+        }
+    }
+
+    /// The underlying error without the attached diagnostics
+    pub(crate) fn root(&self) -> &DailyRoutineError {
+        match self {
+            DailyRoutineError::Diagnosed(e, _) => e.root(),
+            e => e,
+        }
+    }
 }
 
 impl ExtractSpanTrace for DailyRoutineError {
@@ -46,11 +80,15 @@ impl ExtractSpanTrace for DailyRoutineError {
             DailyRoutineError::MinecraftChart(e) => e.span_trace(),
             DailyRoutineError::ShutdownMinecraftServer(_, e) => e.span_trace(),
             DailyRoutineError::RelaunchMinecraftServer(_, e) => e.span_trace(),
+            DailyRoutineError::MovePlayers(_, _, e) => e.span_trace(),
+            DailyRoutineError::CanaryVerification(_, e) => e.span_trace(),
             DailyRoutineError::WaitJobFinished(_, e) => e.span_trace(),
             DailyRoutineError::CustomJobHasFailure(_, _, span_trace) => Some(span_trace),
+            DailyRoutineError::PodJob(_, e) => e.span_trace(),
             DailyRoutineError::KubeClient(e) => e.span_trace(),
             DailyRoutineError::TaskJoin(_) => None,
             DailyRoutineError::InvalidTaskDag(e) => e.span_trace(),
+            DailyRoutineError::Diagnosed(e, _) => e.span_trace(),
         }
     }
 }
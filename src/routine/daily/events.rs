@@ -0,0 +1,35 @@
+use std::fmt::Display;
+
+use k8s_openapi::api::apps::v1::StatefulSet;
+use kcr_argoproj_io::v1alpha1::applications::Application;
+
+use crate::kubernetes_objects::ARGOCD_NAMESPACE;
+use crate::kubernetes_objects::event_recorder::RoutineAction;
+
+use super::DailyRoutineContext;
+
+impl DailyRoutineContext {
+    /// Records the step as an event on the StatefulSet of a chart.
+    pub(super) async fn record_statefulset_event<E: Display>(
+        &self,
+        sts_name: &str,
+        action: RoutineAction,
+        result: Result<String, E>,
+    ) {
+        self.events
+            .publish::<StatefulSet, E>(&self.config.namespace, sts_name, action, result)
+            .await;
+    }
+
+    /// Records the step as an event on an ArgoCD Application.
+    pub(super) async fn record_application_event<E: Display>(
+        &self,
+        application: &str,
+        action: RoutineAction,
+        result: Result<String, E>,
+    ) {
+        self.events
+            .publish::<Application, E>(ARGOCD_NAMESPACE, application, action, result)
+            .await;
+    }
+}
@@ -1,9 +1,15 @@
+use std::iter;
+
 use super::DailyRoutineContext;
+use super::report::{ErrorReport, TaskStatus};
 
-use tracing::error;
+use tracing::{error, warn};
 use tracing::{info, instrument};
 use tracing_error::ExtractSpanTrace;
 
+use crate::kubernetes_objects::event_recorder::RoutineAction;
+use crate::kubernetes_objects::job::{delete_job, garbage_collect_jobs};
+use crate::logging::log_error;
 use crate::routine::daily::DailyRoutineError;
 
 impl DailyRoutineContext {
@@ -12,22 +18,127 @@ impl DailyRoutineContext {
         &self,
         result: Result<(), DailyRoutineError>,
     ) -> Result<(), DailyRoutineError> {
-        info!("Tearup all ArgoCD applications of minecraft charts...");
-        if let Err(e) = self.config.mcproxy.write().await.release().await {
-            error!("Failed to release mcproxy: {}", e);
-            if let Some(span_trace) = e.span_trace() {
-                eprintln!("\n{}\n", color_spantrace::colorize(span_trace));
+        let running_jobs = std::mem::take(
+            &mut *self
+                .running_jobs
+                .lock()
+                .expect("running jobs lock poisoned"),
+        );
+        for job_name in running_jobs {
+            warn!("Deleting job '{job_name}' left running by the cancelled routine...");
+            if let Err(e) = delete_job(self.client.clone(), &self.config.namespace, &job_name).await
+            {
+                error!("Failed to delete job '{job_name}': {}", e);
             }
         }
-        for (name, mcserver) in self.config.mcservers.iter() {
-            if let Err(e) = mcserver.write().await.release().await {
-                error!("Failed to release mcserver '{name}': {}", e);
-                if let Some(span_trace) = e.span_trace() {
-                    eprintln!("\n{}\n", color_spantrace::colorize(span_trace));
+
+        // ArgoCD would sync the revision which broke the canary to every other server
+        let canary = self.config.canary.as_ref().map(|c| c.mcserver.as_str());
+        let canary_failed =
+            self.recorder().task_status("verify_canary") == Some(TaskStatus::Failed);
+        if canary_failed {
+            warn!(
+                "The canary failed verification, so automated sync stays disabled for the other charts."
+            );
+        }
+        let mut held = Vec::new();
+
+        info!("Tearup all ArgoCD applications of minecraft charts...");
+        let charts = iter::once(("mcproxy", &self.config.mcproxy)).chain(
+            self.config
+                .mcservers
+                .iter()
+                .map(|(name, mcserver)| (name.as_str(), mcserver)),
+        );
+        for (name, chart) in charts {
+            let mut chart = chart.write().await;
+            let application = chart.argocd_name().await;
+            let automated_sync = !canary_failed || Some(name) == canary;
+            let release = chart.release(automated_sync);
+            tokio::pin!(release);
+            let alert_after = self.config.notifications.restore_alert_after;
+            let result = tokio::select! {
+                result = &mut release => result,
+                _ = tokio::time::sleep(alert_after) => {
+                    warn!(
+                        "Restoring ArgoCD application '{application}' takes longer than {:?}...",
+                        alert_after
+                    );
+                    self.notifications
+                        .alert(
+                            &self.run_id,
+                            format!("ArgoCD restore of {application} is stuck"),
+                            format!("Restoring '{name}' has not finished within {alert_after:?}."),
+                        )
+                        .await;
+                    release.await
+                }
+            };
+            if self.recorder().was_torn_down(&application) {
+                self.record_application_event(
+                    &application,
+                    RoutineAction::Restore,
+                    result.as_ref().map(|_| {
+                        if automated_sync {
+                            format!(
+                                "Automated sync enabled again by man10routine run {}",
+                                self.run_id
+                            )
+                        } else {
+                            format!(
+                                "Released without automated sync by man10routine run {} because the canary failed verification",
+                                self.run_id
+                            )
+                        }
+                    }),
+                )
+                .await;
+            }
+            match result {
+                Ok(()) if automated_sync => self.recorder().record_restore(application),
+                Ok(()) => {
+                    self.recorder().record_hold(application.clone());
+                    held.push(application);
+                }
+                Err(e) => {
+                    log_error(&format!("Failed to release '{name}': {e}"), e.span_trace());
+                    self.recorder().record_error(ErrorReport::new(
+                        format!("release/{name}"),
+                        &e,
+                        e.span_trace(),
+                    ));
+                    self.notifications
+                        .alert(
+                            &self.run_id,
+                            format!("ArgoCD restore of {application} failed"),
+                            e.to_string(),
+                        )
+                        .await;
                 }
             }
         }
 
+        if !held.is_empty() {
+            self.notifications
+                .alert(
+                    &self.run_id,
+                    "Automated sync left disabled after the canary failure".to_string(),
+                    format!(
+                        "Enable it again once the revision is fixed: {}",
+                        held.join(", ")
+                    ),
+                )
+                .await;
+        }
+
+        if let Some(retention) = self.config.successful_jobs_retention {
+            match garbage_collect_jobs(self.client.clone(), &self.config.namespace, retention).await
+            {
+                Ok(deleted) => info!("Deleted {deleted} successful jobs older than {retention:?}."),
+                Err(e) => error!("Failed to garbage-collect successful jobs: {}", e),
+            }
+        }
+
         result
     }
 }
@@ -1,58 +1,161 @@
+mod diagnostics;
 pub mod error;
+mod events;
 mod finalizer;
 mod phase_argocd_teardown;
 mod phase_execute_job;
+mod phase_hooks;
+mod phase_move_players;
 mod phase_relaunch_mcproxy;
 mod phase_relaunch_mcserver;
 mod phase_shutdown_mcproxy;
 mod phase_shutdown_mcservers;
+mod phase_verify_canary;
+pub(crate) mod report;
 
+use std::collections::BTreeSet;
 use std::iter;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use futures::{StreamExt, future, stream};
+use k8s_openapi::chrono::{DateTime, NaiveDate, Utc};
 use kube::Client;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 
 use crate::config::Config;
+use crate::config::notifications::NotificationKind;
+use crate::kubernetes_objects::custom_job::{JobRef, RelaunchTarget, RoutinePhase};
+use crate::kubernetes_objects::event_recorder::EventRecorder;
+use crate::kubernetes_objects::hook::HookPoint;
+use crate::kubernetes_objects::minecraft_chart::SharedMinecraftChart;
+use crate::notification::{Notification, Notifications};
+use crate::progress::Progress;
 use crate::scheduler::{Scheduler, Shutdown, TaskSpec};
 
 use self::error::DailyRoutineError;
 use self::phase_argocd_teardown::task_phase_argocd_teardown;
-use self::phase_execute_job::task_execute_job;
+use self::phase_execute_job::{GLOBAL_JOB_OWNER, task_execute_job};
+use self::phase_hooks::task_run_hooks;
+use self::phase_move_players::task_move_players;
 use self::phase_relaunch_mcproxy::task_phase_relaunch_mcproxy;
 use self::phase_relaunch_mcserver::task_relaunch_mcserver;
 use self::phase_shutdown_mcproxy::task_phase_shutdown_mcproxy;
 use self::phase_shutdown_mcservers::task_shutdown_mcserver;
+use self::phase_verify_canary::task_verify_canary;
+use self::report::RunRecorder;
 
 #[derive(Clone)]
 pub(crate) struct DailyRoutineContext {
     pub(crate) config: Arc<Config>,
     pub(crate) client: Client,
+
+    /// Time at which this run started
+    pub(crate) started_at: DateTime<Utc>,
+
+    /// Identifier of this run, used to name and label the created Jobs
+    pub(crate) run_id: String,
+
+    /// Jobs which have been created and not yet finished, deleted by the finalizer if the
+    /// routine is cancelled while they are running
+    pub(crate) running_jobs: Arc<Mutex<BTreeSet<String>>>,
+
+    /// Task results, Jobs, errors and ArgoCD applications collected for the run report
+    pub(crate) recorder: Arc<Mutex<RunRecorder>>,
+
+    /// Sinks to which the start, the summary and alerts of the run are posted
+    pub(crate) notifications: Notifications,
+
+    /// Kubernetes Events recorded on the StatefulSets, Applications and Jobs of the run
+    pub(crate) events: EventRecorder,
+
+    /// Live view of the tasks on the terminal, if requested and stdout is one
+    pub(crate) progress: Option<Progress>,
 }
 
 impl DailyRoutineContext {
-    pub(crate) fn new(config: Config, client: Client) -> DailyRoutineContext {
+    pub(crate) fn new(
+        config: Config,
+        client: Client,
+        progress: Option<Progress>,
+    ) -> DailyRoutineContext {
+        let started_at = Utc::now();
+        let notifications = Notifications::new(&config.notifications);
+        let events = EventRecorder::new(client.clone());
         DailyRoutineContext {
             config: Arc::new(config),
             client,
+            started_at,
+            run_id: started_at.format("%Y%m%d-%H%M%S").to_string(),
+            running_jobs: Arc::new(Mutex::new(BTreeSet::new())),
+            recorder: Arc::new(Mutex::new(RunRecorder::default())),
+            notifications,
+            events,
+            progress,
         }
     }
 
-    #[instrument("daily_routine", skip(self))]
+    #[instrument("daily_routine", skip(self), fields(run_id = %self.run_id))]
     pub(crate) async fn run(&self) -> Result<(), DailyRoutineError> {
         info!("Starting daily routine...");
 
-        let shutdown = Shutdown::new();
         let tasks = build_daily_tasks(self).await;
-        let scheduler = Scheduler::from_tasks(tasks, shutdown)?;
+        self.run_tasks("Daily routine", tasks).await
+    }
+
+    #[instrument("rolling_routine", skip(self), fields(run_id = %self.run_id))]
+    pub(crate) async fn run_rolling(
+        &self,
+        wave_size: Option<usize>,
+    ) -> Result<(), DailyRoutineError> {
+        info!("Starting rolling restart routine...");
+
+        let tasks = build_rolling_tasks(self, wave_size).await;
+        self.run_tasks("Rolling restart routine", tasks).await
+    }
+
+    /// Date on which this run started, in the configured time zone
+    pub(crate) fn run_date(&self) -> NaiveDate {
+        self.started_at
+            .with_timezone(&self.config.time_zone)
+            .date_naive()
+    }
+
+    async fn run_tasks(
+        &self,
+        routine_name: &str,
+        tasks: Vec<TaskSpec<DailyRoutineContext, DailyRoutineError>>,
+    ) -> Result<(), DailyRoutineError> {
+        self.notifications
+            .notify(Notification {
+                kind: NotificationKind::Start,
+                run_id: self.run_id.clone(),
+                title: format!("{routine_name} started"),
+                text: format!("{} tasks scheduled.", tasks.len()),
+                succeeded: None,
+            })
+            .await;
+
+        let shutdown = Shutdown::new();
+        let tasks = tasks.into_iter().map(|t| self.track_task(t)).collect();
+        let mut scheduler = Scheduler::from_tasks(tasks, shutdown)?;
+        let display = self
+            .progress
+            .as_ref()
+            .map(|progress| progress.start(routine_name, scheduler.stages()));
+        if let Some(display) = &display {
+            scheduler = scheduler.with_events(display.events());
+        }
         let result = match scheduler.run(self.clone()).await {
             Ok(inner) => inner,
             Err(join_err) => Err(DailyRoutineError::TaskJoin(join_err)),
         };
+        if let Some(display) = display {
+            display.finish().await;
+        }
+        self.alert_failed_tasks().await;
 
         if result.is_ok() {
-            info!("Daily routine completed successfully.");
+            info!("{routine_name} completed successfully.");
         }
 
         self.finalizer(result).await
@@ -70,89 +173,353 @@ async fn build_daily_tasks(
         task_phase_argocd_teardown,
     ));
 
+    tasks.push(task_run_hooks(
+        "before_shutdown_mcproxy".to_string(),
+        vec!["argocd_teardown".to_string()],
+        Arc::downgrade(&ctx.config.mcproxy),
+        HookPoint::BeforeShutdown,
+    ));
+
     tasks.push(TaskSpec::new(
         "shutdown_mcproxy",
-        vec!["argocd_teardown".to_string()],
+        vec!["before_shutdown_mcproxy".to_string()],
         task_phase_shutdown_mcproxy,
     ));
 
-    ctx.config
-        .mcservers
-        .iter()
-        .map(|(name, mcserver)| {
-            task_shutdown_mcserver(
-                format!("shutdown_mcserver/{}", name),
-                Arc::downgrade(mcserver),
-            )
-        })
-        .for_each(|task| tasks.push(task));
-
-    stream::iter(ctx.config.mcservers.iter())
-        .then(async |(name, mcserver)| {
-            let weak_mcserver = Arc::downgrade(mcserver);
-            mcserver
-                .read()
-                .await
-                .jobs_after_snapshot
-                .clone()
-                .into_iter()
-                .map(move |(job_name, job)| (name.clone(), weak_mcserver.clone(), job_name, job))
-        })
-        .flat_map_unordered(None, stream::iter)
-        .map(|(mcserver_name, mcserver, job_name, job)| {
-            TaskSpec::new(
-                format!("execute_job/after_snapshot/{}/{}", mcserver_name, job_name),
-                job.dependencies
-                    .iter()
-                    .map(|d| format!("execute_job/after_snapshot/{}/{}", mcserver_name, d))
-                    .chain(iter::once(format!("shutdown_mcserver/{}", mcserver_name)))
-                    .collect::<Vec<_>>(),
-                move |ctx| task_execute_job(ctx, mcserver, job_name, job),
-            )
-        })
-        .for_each(|task| {
-            tasks.push(task);
-            future::ready(())
-        })
-        .await;
-
-    stream::iter(ctx.config.mcservers.iter())
-        .then(async |(name, mcserver)| {
-            let weak_mcserver = Arc::downgrade(mcserver);
-            let deps: Vec<String> = mcserver
-                .read()
-                .await
-                .jobs_after_snapshot
-                .clone()
-                .into_keys()
-                .map(|d| format!("execute_job/after_snapshot/{}/{}", name, d))
-                .chain(iter::once(format!("shutdown_mcserver/{}", name)))
-                .collect();
-            (name.clone(), weak_mcserver, deps)
-        })
-        .map(|(mcserver_name, mcserver, deps)| {
-            TaskSpec::new(
-                format!("relaunch_mcserver/{}", mcserver_name),
-                deps,
-                move |ctx| task_relaunch_mcserver(ctx, mcserver),
+    let canary = ctx.config.canary.as_ref().map(|c| c.mcserver.as_str());
+    if let Some(canary) = canary {
+        tasks.push(TaskSpec::new(
+            "verify_canary",
+            vec![format!("after_relaunch_mcserver/{}", canary)],
+            task_verify_canary,
+        ));
+    }
+
+    for (name, mcserver) in ctx.config.mcservers.iter() {
+        tasks.extend(
+            build_mcserver_tasks(
+                ctx,
+                name,
+                mcserver,
+                vec!["shutdown_mcproxy".to_string()],
+                canary_relaunch_deps(canary, name)
+                    .into_iter()
+                    .chain(global_job_relaunch_deps(
+                        ctx,
+                        &RelaunchTarget::Mcserver(name.clone()),
+                    ))
+                    .collect(),
             )
-        })
-        .for_each(|task| {
-            tasks.push(task);
-            future::ready(())
-        })
-        .await;
+            .await,
+        );
+    }
+
+    for (job_name, global_job) in ctx.config.jobs.iter() {
+        let job_name = job_name.clone();
+        let job = global_job.job.clone();
+        tasks.push(task_execute_job(
+            format!("execute_job/global/{}", job_name),
+            global_job
+                .references
+                .iter()
+                .flat_map(|r| job_ref_task_names(ctx, r))
+                .chain(iter::once("argocd_teardown".to_string()))
+                .collect(),
+            GLOBAL_JOB_OWNER.to_string(),
+            job_name,
+            job,
+            ctx.run_date(),
+        ));
+    }
+
+    let mcproxy_job_tasks = build_mcproxy_job_tasks(ctx).await;
+    let mcproxy_job_task_names: Vec<String> =
+        mcproxy_job_tasks.iter().map(|t| t.name.clone()).collect();
+    tasks.extend(mcproxy_job_tasks);
 
     tasks.push(TaskSpec::new(
         "relaunch_mcproxy",
         stream::iter(ctx.config.mcservers.iter())
             .filter(|(_, mcserver)| async { mcserver.read().await.required_to_start })
-            .map(|(name, _)| format!("relaunch_mcserver/{}", name))
+            .map(|(name, _)| format!("after_relaunch_mcserver/{}", name))
             .chain(stream::once(future::ready("shutdown_mcproxy".to_string())))
+            .chain(stream::iter(canary.map(|_| "verify_canary".to_string())))
+            .chain(stream::iter(mcproxy_job_task_names))
+            .chain(stream::iter(global_job_relaunch_deps(
+                ctx,
+                &RelaunchTarget::Mcproxy,
+            )))
             .collect::<Vec<_>>()
             .await,
         move |ctx| task_phase_relaunch_mcproxy(ctx),
     ));
 
+    tasks.push(task_run_hooks(
+        "after_relaunch_mcproxy".to_string(),
+        vec!["relaunch_mcproxy".to_string()],
+        Arc::downgrade(&ctx.config.mcproxy),
+        HookPoint::AfterRelaunch,
+    ));
+
+    tasks
+}
+
+/// Builds the tasks of a rolling restart, which restarts mcservers wave by wave while the
+/// mcproxy stays online.
+///
+/// Players are moved to the fallback server before each mcserver is shut down, and the next
+/// wave starts only after every mcserver of the previous wave has been relaunched.
+async fn build_rolling_tasks(
+    ctx: &DailyRoutineContext,
+    wave_size: Option<usize>,
+) -> Vec<TaskSpec<DailyRoutineContext, DailyRoutineError>> {
+    let rolling = ctx
+        .config
+        .rolling
+        .as_ref()
+        .expect("rolling routine requires rolling configuration");
+    let mut tasks = Vec::new();
+
+    if !ctx.config.jobs.is_empty() {
+        warn!("Top-level jobs are only run by the daily routine and are skipped.");
+    }
+    if !ctx
+        .config
+        .mcproxy
+        .read()
+        .await
+        .jobs_after_snapshot
+        .is_empty()
+    {
+        warn!("The mcproxy stays online during a rolling restart; its jobs are skipped.");
+    }
+
+    tasks.push(TaskSpec::new(
+        "argocd_teardown",
+        Vec::<String>::new(),
+        task_phase_argocd_teardown,
+    ));
+
+    let canary = ctx.config.canary.as_ref().map(|c| c.mcserver.clone());
+    let waves = canary.iter().map(|c| vec![c.clone()]).chain(rolling_waves(
+        ctx.config
+            .mcservers
+            .keys()
+            .filter(|name| Some(*name) != canary.as_ref()),
+        wave_size.unwrap_or(rolling.wave_size),
+        &rolling.fallback_server,
+    ));
+
+    let mut wave_deps = vec!["argocd_teardown".to_string()];
+    for wave in waves {
+        for name in wave.iter() {
+            let mcserver = &ctx.config.mcservers[name];
+            let shutdown_deps = if *name == rolling.fallback_server {
+                warn!(
+                    "mcserver '{}' is the fallback server; players on it will be disconnected when it restarts.",
+                    name
+                );
+                wave_deps.clone()
+            } else {
+                let move_task_name = format!("move_players/{}", name);
+                tasks.push(task_move_players(
+                    move_task_name.clone(),
+                    wave_deps.clone(),
+                    name.clone(),
+                ));
+                vec![move_task_name]
+            };
+            tasks.extend(build_mcserver_tasks(ctx, name, mcserver, shutdown_deps, vec![]).await);
+        }
+        wave_deps = wave
+            .iter()
+            .map(|name| format!("after_relaunch_mcserver/{}", name))
+            .collect();
+        if canary.as_ref().is_some_and(|c| wave.contains(c)) {
+            tasks.push(TaskSpec::new(
+                "verify_canary",
+                wave_deps.clone(),
+                task_verify_canary,
+            ));
+            wave_deps = vec!["verify_canary".to_string()];
+        }
+    }
+
     tasks
 }
+
+/// Splits mcservers into waves of `wave_size`, with the fallback server restarted alone last.
+fn rolling_waves<'a>(
+    mcserver_names: impl Iterator<Item = &'a String>,
+    wave_size: usize,
+    fallback_server: &str,
+) -> Vec<Vec<String>> {
+    let (fallback, others): (Vec<String>, Vec<String>) = mcserver_names
+        .cloned()
+        .partition(|name| name == fallback_server);
+
+    others
+        .chunks(wave_size.max(1))
+        .map(|chunk| chunk.to_vec())
+        .chain((!fallback.is_empty()).then_some(fallback))
+        .collect()
+}
+
+/// Relaunches of every mcserver other than the canary wait for the canary to be verified.
+fn canary_relaunch_deps(canary: Option<&str>, name: &str) -> Vec<String> {
+    match canary {
+        Some(canary) if canary != name => vec!["verify_canary".to_string()],
+        _ => vec![],
+    }
+}
+
+/// Builds the tasks of the mcproxy's `jobs_after_snapshot`, run after the mcproxy has been
+/// shut down.
+async fn build_mcproxy_job_tasks(
+    ctx: &DailyRoutineContext,
+) -> Vec<TaskSpec<DailyRoutineContext, DailyRoutineError>> {
+    let (chart_name, jobs_after_snapshot) = {
+        let read = ctx.config.mcproxy.read().await;
+        (read.name.clone(), read.jobs_after_snapshot.clone())
+    };
+
+    jobs_after_snapshot
+        .into_iter()
+        .map(|(job_name, job)| {
+            task_execute_job(
+                format!("execute_job/mcproxy/{}", job_name),
+                job.dependencies
+                    .iter()
+                    .map(|d| format!("execute_job/mcproxy/{}", d))
+                    .chain(iter::once("shutdown_mcproxy".to_string()))
+                    .collect(),
+                chart_name.clone(),
+                job_name,
+                job,
+                ctx.run_date(),
+            )
+        })
+        .collect()
+}
+
+/// Tasks of the top-level jobs which block the relaunch of `target`.
+fn global_job_relaunch_deps(ctx: &DailyRoutineContext, target: &RelaunchTarget) -> Vec<String> {
+    ctx.config
+        .jobs
+        .iter()
+        .filter(|(_, job)| job.blocks_relaunch.contains(target))
+        .map(|(name, _)| format!("execute_job/global/{}", name))
+        .collect()
+}
+
+/// Tasks which have to finish before a top-level job referencing them can start.
+fn job_ref_task_names(ctx: &DailyRoutineContext, reference: &JobRef) -> Vec<String> {
+    match reference {
+        JobRef::McserverJob { mcserver, job } => {
+            vec![format!("execute_job/after_snapshot/{}/{}", mcserver, job)]
+        }
+        JobRef::McserverDown(mcserver) => vec![format!("shutdown_mcserver/{}", mcserver)],
+        JobRef::Job(job) => vec![format!("execute_job/global/{}", job)],
+        JobRef::Phase(RoutinePhase::McproxyDown) => vec!["shutdown_mcproxy".to_string()],
+        JobRef::Phase(RoutinePhase::AllServersDown) => ctx
+            .config
+            .mcservers
+            .keys()
+            .map(|name| format!("shutdown_mcserver/{}", name))
+            .chain(iter::once("shutdown_mcproxy".to_string()))
+            .collect(),
+    }
+}
+
+/// Builds the hook, shutdown, job and relaunch tasks of a single mcserver.
+///
+/// `shutdown_deps` gates the `before_shutdown` hooks of the mcserver; the shutdown, the jobs, the
+/// relaunch and the `after_relaunch` hooks are chained after them. `extra_relaunch_deps`
+/// additionally gates the relaunch.
+async fn build_mcserver_tasks(
+    ctx: &DailyRoutineContext,
+    name: &str,
+    mcserver: &SharedMinecraftChart,
+    shutdown_deps: Vec<String>,
+    extra_relaunch_deps: Vec<String>,
+) -> Vec<TaskSpec<DailyRoutineContext, DailyRoutineError>> {
+    let mut tasks = Vec::new();
+    let weak_mcserver = Arc::downgrade(mcserver);
+    let (chart_name, jobs_after_snapshot) = {
+        let read = mcserver.read().await;
+        (read.name.clone(), read.jobs_after_snapshot.clone())
+    };
+
+    tasks.push(task_run_hooks(
+        format!("before_shutdown_mcserver/{}", name),
+        shutdown_deps,
+        weak_mcserver.clone(),
+        HookPoint::BeforeShutdown,
+    ));
+
+    tasks.push(task_shutdown_mcserver(
+        format!("shutdown_mcserver/{}", name),
+        vec![format!("before_shutdown_mcserver/{}", name)],
+        weak_mcserver.clone(),
+    ));
+
+    let relaunch_deps: Vec<String> = jobs_after_snapshot
+        .keys()
+        .map(|d| format!("execute_job/after_snapshot/{}/{}", name, d))
+        .chain(iter::once(format!("shutdown_mcserver/{}", name)))
+        .chain(extra_relaunch_deps)
+        .collect();
+
+    for (job_name, job) in jobs_after_snapshot {
+        tasks.push(task_execute_job(
+            format!("execute_job/after_snapshot/{}/{}", name, job_name),
+            job.dependencies
+                .iter()
+                .map(|d| format!("execute_job/after_snapshot/{}/{}", name, d))
+                .chain(iter::once(format!("shutdown_mcserver/{}", name)))
+                .collect(),
+            chart_name.clone(),
+            job_name,
+            job,
+            ctx.run_date(),
+        ));
+    }
+
+    tasks.push(task_run_hooks(
+        format!("after_relaunch_mcserver/{}", name),
+        vec![format!("relaunch_mcserver/{}", name)],
+        weak_mcserver.clone(),
+        HookPoint::AfterRelaunch,
+    ));
+
+    tasks.push(TaskSpec::new(
+        format!("relaunch_mcserver/{}", name),
+        relaunch_deps,
+        move |ctx| task_relaunch_mcserver(ctx, weak_mcserver),
+    ));
+
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_waves() {
+        let names = ["lobby", "survival", "creative", "event", "limbo"].map(String::from);
+
+        assert_eq!(
+            rolling_waves(names.iter(), 2, "limbo"),
+            vec![
+                vec!["lobby".to_string(), "survival".to_string()],
+                vec!["creative".to_string(), "event".to_string()],
+                vec!["limbo".to_string()],
+            ]
+        );
+        assert_eq!(
+            rolling_waves(names.iter(), 10, "unmanaged"),
+            vec![names.to_vec()]
+        );
+    }
+}
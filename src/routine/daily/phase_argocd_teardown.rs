@@ -7,18 +7,26 @@ use futures::stream;
 use tokio::time::{Duration, sleep};
 use tracing::{Instrument, error, info, instrument};
 
+use crate::kubernetes_objects::event_recorder::RoutineAction;
 use crate::kubernetes_objects::minecraft_chart::MinecraftChartError;
 use crate::routine::daily::DailyRoutineError;
 
 #[instrument(name = "phase_argocd_teardown", skip(ctx))]
 async fn phase_argocd_teardown(ctx: DailyRoutineContext) -> Result<(), DailyRoutineError> {
     info!("Teardown all ArgoCD applications of minecraft charts...");
-    ctx.config
-        .mcproxy
-        .write()
-        .await
-        .argocd_teardown(ctx.client.clone())
-        .await?;
+    {
+        let mut mcproxy = ctx.config.mcproxy.write().await;
+        let result = mcproxy.argocd_teardown(ctx.client.clone()).await;
+        let application = mcproxy.argocd_name().await;
+        ctx.record_application_event(
+            &application,
+            RoutineAction::Teardown,
+            result.as_ref().map(|_| teardown_note(&ctx)),
+        )
+        .await;
+        result?;
+        ctx.recorder().record_teardown(application);
+    }
     info!("Teardown all mcservers...");
     let mcservers: Vec<(String, _)> = ctx
         .config
@@ -32,9 +40,22 @@ async fn phase_argocd_teardown(ctx: DailyRoutineContext) -> Result<(), DailyRout
             let name = name.clone();
             let client = ctx.client.clone();
             let mcserver = mcserver.clone();
+            let ctx = ctx.clone();
             async move {
-                match mcserver.write().await.argocd_teardown(client).await {
-                    Ok(_) => Ok(()),
+                let mut mcserver = mcserver.write().await;
+                let result = mcserver.argocd_teardown(client).await;
+                let application = mcserver.argocd_name().await;
+                ctx.record_application_event(
+                    &application,
+                    RoutineAction::Teardown,
+                    result.as_ref().map(|_| teardown_note(&ctx)),
+                )
+                .await;
+                match result {
+                    Ok(_) => {
+                        ctx.recorder().record_teardown(application);
+                        Ok(())
+                    }
                     Err(e) => {
                         error!("Failed to teardown mcserver '{name}': {}", e);
                         Err(e)
@@ -53,6 +74,13 @@ async fn phase_argocd_teardown(ctx: DailyRoutineContext) -> Result<(), DailyRout
     Ok(())
 }
 
+fn teardown_note(ctx: &DailyRoutineContext) -> String {
+    format!(
+        "Automated sync disabled by man10routine run {} until the routine ends",
+        ctx.run_id
+    )
+}
+
 pub(crate) fn task_phase_argocd_teardown(
     ctx: DailyRoutineContext,
 ) -> TaskFuture<DailyRoutineError> {
@@ -1,105 +1,496 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
 use k8s_openapi::api::batch::v1::Job;
-use kube::Api;
+use k8s_openapi::chrono::NaiveDate;
 use kube::api::PostParams;
-use tracing::{Instrument, error, info, instrument, trace_span};
+use kube::{Api, Resource};
+use thiserror::Error;
+use tracing::{Instrument, error, info, instrument, trace_span, warn};
 use tracing_error::SpanTrace;
 
+use crate::config::template::substitute_job;
 use crate::error::SpannedExt;
 use crate::kubernetes_objects::MANAGEER_ROLE_NAME;
-use crate::kubernetes_objects::custom_job::CustomJob;
-use crate::kubernetes_objects::job::wait_until_job_finished;
-use crate::kubernetes_objects::minecraft_chart::WeakMinecraftChart;
-use crate::scheduler::TaskFuture;
+use crate::kubernetes_objects::custom_job::{CustomJob, JobKind, RetryOn};
+use crate::kubernetes_objects::event_recorder::RoutineAction;
+use crate::kubernetes_objects::job::{
+    JobOutcome, WaitJobFinishedError, delete_job, prepare_job_manifest, wait_until_job_finished,
+};
+use crate::kubernetes_objects::job_logs::JobLogFollower;
+use crate::kubernetes_objects::pod::{PodExecError, exec_in_pod};
+use crate::scheduler::TaskSpec;
 
 use super::DailyRoutineContext;
 use super::error::DailyRoutineError;
+use super::report::{ErrorReport, JobReport};
+
+/// Chart name used in the names and labels of top-level jobs
+pub(super) const GLOBAL_JOB_OWNER: &str = "global";
+
+#[derive(Error, Debug)]
+pub enum PodJobError {
+    #[error("Command cannot be executed: {0}")]
+    Exec(#[from] PodExecError),
+
+    #[error("Command failed: {message}\n{stderr}")]
+    CommandFailed { message: String, stderr: String },
+
+    #[error("Output does not contain '{expected}': {output}")]
+    UnexpectedOutput { expected: String, output: String },
+
+    #[error("Command did not finish within {0:?}")]
+    Timeout(Duration),
+}
 
-#[instrument("phase_execute_job", skip(ctx, mcserver, job))]
-async fn execute_job(
+/// `chart_name` is the internal name of the chart owning the job, or [`GLOBAL_JOB_OWNER`] for
+/// top-level jobs.
+#[instrument("phase_execute_job", skip(ctx, job))]
+pub(super) async fn execute_job(
     ctx: DailyRoutineContext,
-    mcserver: WeakMinecraftChart,
+    chart_name: String,
     job_name: String,
     job: CustomJob,
 ) -> Result<(), DailyRoutineError> {
-    let client = ctx.client.clone();
     let namespace = ctx.config.namespace.clone();
 
-    let mcserver = mcserver.upgrade().expect("MinecraftChart has been dropped");
-    let read = &mcserver.read().await;
-
-    let mcserver_name = &read.name;
-
+    // `chart_name` and `job_name` are recorded by the `phase_execute_job` span
     let span = trace_span!(
         "execute_job",
         kubernetes_namespace = %namespace,
-        mcserver_name = %mcserver_name,
-        job_name = %job_name
+        mcserver_name = %chart_name,
     );
 
-    let result = async move {
-        // Create the Job in Kubernetes
-        let jobs_api: Api<Job> = Api::namespaced(client.clone(), &namespace);
+    let result = run_with_retries(&job, &job_name, |attempt| {
+        run_job_attempt(&ctx, &chart_name, &job_name, &job, attempt)
+    })
+    .instrument(span)
+    .await;
+
+    match result {
+        Ok(_) => {
+            info!(
+                "Job '{}' for '{}' executed successfully.",
+                job_name, chart_name
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!(
+                "Failed to execute job '{}' for '{}': {}",
+                job_name, chart_name, e
+            );
+            if job.required {
+                Err(e)
+            } else {
+                ctx.recorder().record_error(ErrorReport::from_routine_error(
+                    format!("job/{chart_name}/{job_name}"),
+                    &e,
+                ));
+                ctx.notifications
+                    .alert(
+                        &ctx.run_id,
+                        format!("Job {job_name} for {chart_name} failed"),
+                        format!("{e}\nThe routine continues since the job is not required."),
+                    )
+                    .await;
+                Ok(())
+            }
+        }
+    }
+}
 
-        let post_params = PostParams {
-            field_manager: Some(MANAGEER_ROLE_NAME.to_string()),
-            ..Default::default()
+/// Runs attempts of the job until one succeeds, fails in a way not listed in `retry_on`, or
+/// `retries` are exhausted.
+async fn run_with_retries<F, Fut>(
+    job: &CustomJob,
+    job_name: &str,
+    mut run_attempt: F,
+) -> Result<(), DailyRoutineError>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<(), DailyRoutineError>>,
+{
+    let mut attempt = 0;
+    loop {
+        if job.retries > 0 {
+            info!(
+                "Starting attempt {}/{} of job '{}'...",
+                attempt + 1,
+                job.retries + 1,
+                job_name
+            );
+        }
+
+        let result = run_attempt(attempt).await;
+        let Err(e) = &result else {
+            return result;
         };
 
-        let job_created = async {
-            jobs_api
-                .create(&post_params, &job.manifest)
-                .await
-                .with_span_trace()
+        let retryable = retry_kind(e).is_some_and(|kind| job.retry_on.contains(&kind));
+        if !retryable || attempt >= job.retries {
+            return result;
         }
-        .instrument(trace_span!("create_job"))
-        .await?;
 
-        let created_job_name = job_created.metadata.name.as_deref().unwrap_or("<unknown>");
+        attempt += 1;
+        let backoff = job.backoff_for_attempt(attempt);
+        warn!(
+            "Attempt {} of job '{}' failed: {}. Retrying in {:?}...",
+            attempt, job_name, e, backoff
+        );
+        tokio::time::sleep(backoff).await;
+    }
+}
 
-        match wait_until_job_finished(
-            client,
-            &namespace,
-            created_job_name,
-            &job.completion_polling,
-        )
-        .await
-        {
-            Ok(status) if status.failed == Some(0) || status.failed.is_none() => Ok(()),
-            Ok(status) => Err(DailyRoutineError::CustomJobHasFailure(
-                created_job_name.to_string(),
-                status,
-                SpanTrace::capture(),
-            )),
-            Err(e) => Err(e)
-                .map_err(|e| DailyRoutineError::WaitJobFinished(created_job_name.to_string(), e)),
+/// Runs one attempt of the job until it has finished
+#[instrument("job_attempt", skip(ctx, chart_name, job_name, job))]
+async fn run_job_attempt(
+    ctx: &DailyRoutineContext,
+    chart_name: &str,
+    job_name: &str,
+    job: &CustomJob,
+    attempt: u32,
+) -> Result<(), DailyRoutineError> {
+    let timeout = job.completion_polling.max_wait;
+    match &job.kind {
+        JobKind::KubernetesJob {
+            manifest,
+            templated,
+        } => {
+            let manifest = if *templated {
+                substitute_job(manifest, &runtime_variables(ctx, chart_name))
+            } else {
+                manifest.as_ref().clone()
+            };
+            run_kubernetes_job(ctx, chart_name, job_name, job, &manifest, attempt).await
+        }
+        JobKind::Exec(step) => {
+            let commands = vec![(step.command.clone(), None)];
+            run_pod_commands(ctx, job_name, &step.pod, &step.container, commands, timeout).await
+        }
+        JobKind::Rcon(step) => {
+            let commands = step
+                .commands
+                .iter()
+                .map(|c| (c.argv(), c.expect_output.clone()))
+                .collect();
+            run_pod_commands(ctx, job_name, &step.pod, &step.container, commands, timeout).await
         }
     }
-    .instrument(span)
+}
+
+/// Creates the Job from `manifest` and waits until it has finished
+async fn run_kubernetes_job(
+    ctx: &DailyRoutineContext,
+    chart_name: &str,
+    job_name: &str,
+    job: &CustomJob,
+    manifest: &Job,
+    attempt: u32,
+) -> Result<(), DailyRoutineError> {
+    let client = ctx.client.clone();
+    let namespace = &ctx.config.namespace;
+
+    // Create the Job in Kubernetes
+    let jobs_api: Api<Job> = Api::namespaced(client.clone(), namespace);
+
+    let post_params = PostParams {
+        field_manager: Some(MANAGEER_ROLE_NAME.to_string()),
+        ..Default::default()
+    };
+
+    let manifest = prepare_job_manifest(manifest, &ctx.run_id, chart_name, job_name, attempt);
+
+    let job_created = async {
+        jobs_api
+            .create(&post_params, &manifest)
+            .await
+            .with_span_trace()
+    }
+    .instrument(trace_span!("create_job"))
+    .await?;
+
+    let created_job_name = job_created.metadata.name.as_deref().unwrap_or("<unknown>");
+    ctx.running_jobs
+        .lock()
+        .expect("running jobs lock poisoned")
+        .insert(created_job_name.to_string());
+
+    let log_follower = JobLogFollower::spawn(
+        client.clone(),
+        namespace,
+        created_job_name,
+        ctx.config.job_logs_dir.clone(),
+    );
+    let wait_result = wait_until_job_finished(
+        client.clone(),
+        namespace,
+        created_job_name,
+        &job.completion_polling,
+    )
     .await;
+    log_follower.finish(Duration::from_secs(10)).await;
+    let unfinished = wait_result.is_err();
 
-    match result {
-        Ok(_) => {
+    let result = match wait_result {
+        Ok(JobOutcome::Succeeded) => Ok(()),
+        Ok(JobOutcome::Failed(failure)) => Err(DailyRoutineError::CustomJobHasFailure(
+            created_job_name.to_string(),
+            failure,
+            SpanTrace::capture(),
+        )),
+        Err(e) => {
+            Err(e).map_err(|e| DailyRoutineError::WaitJobFinished(created_job_name.to_string(), e))
+        }
+    };
+
+    let result = ctx.diagnose_job(result, created_job_name).await;
+    ctx.events
+        .publish_on(
+            &job_created.object_ref(&()),
+            RoutineAction::RunJob,
+            result.as_ref().map(|_| {
+                format!(
+                    "Job '{}' for '{}' succeeded on attempt {} of man10routine run {}",
+                    job_name,
+                    chart_name,
+                    attempt + 1,
+                    ctx.run_id
+                )
+            }),
+        )
+        .await;
+    ctx.recorder().record_job(JobReport {
+        name: created_job_name.to_string(),
+        chart_name: chart_name.to_string(),
+        job_name: job_name.to_string(),
+        attempt,
+        succeeded: result.is_ok(),
+    });
+
+    if unfinished {
+        warn!("Deleting unfinished job '{created_job_name}'...");
+        if let Err(e) = delete_job(client, namespace, created_job_name).await {
+            error!("Failed to delete unfinished job '{created_job_name}': {e}");
+            return result;
+        }
+    }
+    ctx.running_jobs
+        .lock()
+        .expect("running jobs lock poisoned")
+        .remove(created_job_name);
+
+    result
+}
+
+/// Executes `commands` in order in a running pod, each within `timeout`
+///
+/// A command fails if it exits with a non-zero status or its output does not contain the
+/// expected text. A timed out command is no longer waited for but may keep running in the pod.
+async fn run_pod_commands(
+    ctx: &DailyRoutineContext,
+    job_name: &str,
+    pod_name: &str,
+    container: &str,
+    commands: Vec<(Vec<String>, Option<String>)>,
+    timeout: Duration,
+) -> Result<(), DailyRoutineError> {
+    let span = trace_span!("pod_job", pod_name = %pod_name, container = %container);
+
+    async {
+        for (command, expect_output) in commands {
+            let exec = exec_in_pod(
+                ctx.client.clone(),
+                &ctx.config.namespace,
+                pod_name,
+                container,
+                command.clone(),
+            );
+            let output = match tokio::time::timeout(timeout, exec).await {
+                Ok(output) => output.map_err(PodJobError::from),
+                Err(_) => Err(PodJobError::Timeout(timeout)),
+            }
+            .with_span_trace()?;
+
+            if let Some(message) = output.failure_message() {
+                return Err(PodJobError::CommandFailed {
+                    message,
+                    stderr: output.stderr,
+                })
+                .with_span_trace();
+            }
+            if let Some(expected) = expect_output
+                && !output.stdout.contains(&expected)
+            {
+                return Err(PodJobError::UnexpectedOutput {
+                    expected,
+                    output: output.stdout,
+                })
+                .with_span_trace();
+            }
             info!(
-                "Job '{}' for mcserver '{}' executed successfully.",
-                job_name, mcserver_name
+                "Command {:?} of job '{}' succeeded: {}",
+                command,
+                job_name,
+                output.stdout.trim()
             );
-            Ok(())
         }
-        Err(e) => {
-            error!(
-                "Failed to execute job '{}' for mcserver '{}': {}",
-                job_name, mcserver_name, e
-            );
-            if job.required { Err(e) } else { Ok(()) }
+        Ok(())
+    }
+    .instrument(span)
+    .await
+    .map_err(|e| DailyRoutineError::PodJob(job_name.to_string(), e))
+}
+
+/// Values of the template variables only known when the Job is created
+fn runtime_variables(ctx: &DailyRoutineContext, chart_name: &str) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("run_id".to_string(), ctx.run_id.clone()),
+        (
+            "date".to_string(),
+            ctx.run_date().format("%Y-%m-%d").to_string(),
+        ),
+        (
+            "snapshot_name".to_string(),
+            format!("{}-{}", chart_name, ctx.run_id),
+        ),
+    ])
+}
+
+/// Kind of failure used to decide whether a job is retried
+fn retry_kind(error: &DailyRoutineError) -> Option<RetryOn> {
+    match error.root() {
+        DailyRoutineError::CustomJobHasFailure(..) => Some(RetryOn::Failure),
+        DailyRoutineError::PodJob(_, e) => match e.err {
+            PodJobError::CommandFailed { .. } | PodJobError::UnexpectedOutput { .. } => {
+                Some(RetryOn::Failure)
+            }
+            PodJobError::Timeout(_) => Some(RetryOn::Timeout),
+            PodJobError::Exec(_) => None,
+        },
+        DailyRoutineError::WaitJobFinished(_, e)
+            if matches!(e.err, WaitJobFinishedError::JobCompletionCheckTimeout(_)) =>
+        {
+            Some(RetryOn::Timeout)
         }
+        _ => None,
     }
 }
 
+/// Builds the task of a custom job.
+///
+/// If the `when` condition of the job does not match `run_date`, the task only records the job
+/// as skipped and succeeds, so that the tasks depending on it still run.
 pub(crate) fn task_execute_job(
-    ctx: DailyRoutineContext,
-    mcserver: WeakMinecraftChart,
+    task_name: String,
+    deps: Vec<String>,
+    chart_name: String,
     job_name: String,
     job: CustomJob,
-) -> TaskFuture<DailyRoutineError> {
-    Box::pin(execute_job(ctx, mcserver, job_name, job))
+    run_date: NaiveDate,
+) -> TaskSpec<DailyRoutineContext, DailyRoutineError> {
+    if job.when.matches(run_date) {
+        return TaskSpec::new(task_name, deps, move |ctx| {
+            Box::pin(execute_job(ctx, chart_name, job_name, job))
+        });
+    }
+
+    let name = task_name.clone();
+    TaskSpec::new(task_name, deps, move |ctx: DailyRoutineContext| {
+        Box::pin(async move {
+            log_skipped_job(&chart_name, &job_name, run_date);
+            ctx.record_skip(&name);
+            Ok(())
+        })
+    })
+}
+
+pub(super) fn log_skipped_job(chart_name: &str, job_name: &str, run_date: NaiveDate) {
+    info!(
+        skipped = true,
+        "Job '{}' for '{}' skipped: its 'when' condition does not match {}.",
+        job_name,
+        chart_name,
+        run_date
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+
+    use super::*;
+    use crate::config::condition::JobCondition;
+    use crate::config::polling::PollingConfig;
+    use crate::error::SpannedErr;
+    use crate::kubernetes_objects::custom_job::ExecStep;
+
+    fn job(retries: u32, retry_on: Vec<RetryOn>) -> CustomJob {
+        CustomJob {
+            dependencies: vec![],
+            kind: JobKind::Exec(ExecStep {
+                pod: "mcserver-hub-0".to_string(),
+                container: "mcserver".to_string(),
+                command: vec!["true".to_string()],
+            }),
+            required: true,
+            completion_polling: PollingConfig::default(),
+            retries,
+            retry_backoff: Duration::ZERO,
+            retry_on,
+            when: JobCondition::default(),
+        }
+    }
+
+    fn failure(error: PodJobError) -> DailyRoutineError {
+        let error = SpannedErr {
+            err: error,
+            span_trace: SpanTrace::capture(),
+        };
+        DailyRoutineError::PodJob("notify".to_string(), error)
+    }
+
+    #[tokio::test]
+    async fn test_run_with_retries() {
+        let command_failed = || {
+            Err(failure(PodJobError::CommandFailed {
+                message: "exit status 1".to_string(),
+                stderr: String::new(),
+            }))
+        };
+
+        // Retried until an attempt succeeds
+        let mut attempts = Vec::new();
+        let result = run_with_retries(&job(3, vec![RetryOn::Failure]), "notify", |attempt| {
+            attempts.push(attempt);
+            future::ready(if attempt < 2 {
+                command_failed()
+            } else {
+                Ok(())
+            })
+        })
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(attempts, vec![0, 1, 2]);
+
+        // Gives up once the retries are exhausted
+        let mut attempts = Vec::new();
+        let result = run_with_retries(&job(1, vec![RetryOn::Failure]), "notify", |attempt| {
+            attempts.push(attempt);
+            future::ready(command_failed())
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, vec![0, 1]);
+
+        // Timeouts are not retried unless listed in `retry_on`
+        let mut attempts = Vec::new();
+        let result = run_with_retries(&job(3, vec![RetryOn::Failure]), "notify", |attempt| {
+            attempts.push(attempt);
+            future::ready(Err(failure(PodJobError::Timeout(Duration::from_secs(60)))))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, vec![0]);
+    }
 }
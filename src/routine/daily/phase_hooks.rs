@@ -0,0 +1,48 @@
+use tracing::instrument;
+
+use crate::kubernetes_objects::hook::HookPoint;
+use crate::kubernetes_objects::minecraft_chart::WeakMinecraftChart;
+use crate::scheduler::TaskSpec;
+
+use super::DailyRoutineContext;
+use super::error::DailyRoutineError;
+use super::phase_execute_job::{execute_job, log_skipped_job};
+
+/// Runs the hooks of the chart at `point` in order, as custom jobs named after their position.
+///
+/// A failed hook stops the others only if it is required; the other failures are reported by
+/// [`execute_job`].
+#[instrument("phase_hooks", skip(ctx, chart))]
+async fn run_hooks(
+    ctx: DailyRoutineContext,
+    chart: WeakMinecraftChart,
+    point: HookPoint,
+) -> Result<(), DailyRoutineError> {
+    let chart = chart.upgrade().expect("MinecraftChart has been dropped");
+    let (chart_name, hooks) = {
+        let read = chart.read().await;
+        (read.name.clone(), read.hooks.get(point).to_vec())
+    };
+
+    for (index, hook) in hooks.into_iter().enumerate() {
+        let hook_name = point.hook_name(index);
+        if hook.when.matches(ctx.run_date()) {
+            execute_job(ctx.clone(), chart_name.clone(), hook_name, hook).await?;
+        } else {
+            log_skipped_job(&chart_name, &hook_name, ctx.run_date());
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn task_run_hooks(
+    task_name: String,
+    deps: Vec<String>,
+    chart: WeakMinecraftChart,
+    point: HookPoint,
+) -> TaskSpec<DailyRoutineContext, DailyRoutineError> {
+    TaskSpec::new(task_name, deps, move |ctx| {
+        Box::pin(run_hooks(ctx, chart, point))
+    })
+}
@@ -0,0 +1,74 @@
+use tokio::time::{Duration, sleep};
+use tracing::{Instrument, error, info, instrument, trace_span};
+
+use crate::error::SpannedExt;
+use crate::kubernetes_objects::pod::exec_in_pod;
+use crate::scheduler::TaskSpec;
+
+use super::DailyRoutineContext;
+use super::error::DailyRoutineError;
+
+#[instrument("phase_move_players", skip(ctx))]
+async fn move_players(
+    ctx: DailyRoutineContext,
+    mcserver_name: String,
+) -> Result<(), DailyRoutineError> {
+    let rolling = ctx
+        .config
+        .rolling
+        .as_ref()
+        .expect("move_players task requires rolling configuration");
+    let mcproxy = ctx.config.mcproxy.read().await;
+    let pod_name = format!("{}-0", mcproxy.name);
+    let command = rolling.move_players_argv(&mcserver_name);
+
+    let span = trace_span!(
+        "move_players",
+        kubernetes_namespace = %ctx.config.namespace,
+        pod_name = %pod_name,
+        mcserver_name = %mcserver_name,
+        fallback_server = %rolling.fallback_server,
+        rcon_container = %mcproxy.rcon_container,
+    );
+
+    async {
+        info!(
+            "Moving players from '{}' to '{}'...",
+            mcserver_name, rolling.fallback_server
+        );
+        exec_in_pod(
+            ctx.client.clone(),
+            &ctx.config.namespace,
+            &pod_name,
+            &mcproxy.rcon_container,
+            command,
+        )
+        .await
+        .with_span_trace()
+        .map_err(|e| {
+            DailyRoutineError::MovePlayers(mcserver_name.clone(), mcproxy.name.clone(), e)
+        })?;
+
+        info!("Sleeping for 5 seconds to let players transfer...");
+        sleep(Duration::from_secs(5)).await;
+        Ok(())
+    }
+    .instrument(span)
+    .await
+    .inspect_err(|e| {
+        error!(
+            "Phase 'move_players' for mcserver '{mcserver_name}' failed: {}",
+            e
+        );
+    })
+}
+
+pub(crate) fn task_move_players(
+    task_name: String,
+    deps: Vec<String>,
+    mcserver_name: String,
+) -> TaskSpec<DailyRoutineContext, DailyRoutineError> {
+    TaskSpec::new(task_name, deps, move |ctx| {
+        Box::pin(move_players(ctx, mcserver_name))
+    })
+}
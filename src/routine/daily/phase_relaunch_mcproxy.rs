@@ -1,4 +1,5 @@
 use super::DailyRoutineContext;
+use super::phase_relaunch_mcserver::relaunch_note;
 use crate::config::polling::PollingConfig;
 use crate::scheduler::TaskFuture;
 
@@ -6,6 +7,7 @@ use std::time::Duration;
 
 use tracing::{info, instrument};
 
+use crate::kubernetes_objects::event_recorder::RoutineAction;
 use crate::kubernetes_objects::statefulset::{
     StatefulSetScaleError, scale_statefulset_to_zero, wait_until_statefulset_scaled,
 };
@@ -15,25 +17,38 @@ use crate::routine::daily::error::DailyRoutineError;
 async fn phase_relaunch_mcproxy(ctx: DailyRoutineContext) -> Result<(), DailyRoutineError> {
     let proxy_sts_name = &ctx.config.mcproxy.read().await.name;
     info!("Relaunching proxy server...");
-    scale_statefulset_to_zero(ctx.client.clone(), &ctx.config.namespace, proxy_sts_name, 1)
-        .await
-        .map_err(|e| DailyRoutineError::RelaunchMinecraftServer(proxy_sts_name.to_string(), e))?;
+    let result = async {
+        scale_statefulset_to_zero(ctx.client.clone(), &ctx.config.namespace, proxy_sts_name, 1)
+            .await
+            .map_err(|e| {
+                DailyRoutineError::RelaunchMinecraftServer(proxy_sts_name.to_string(), e)
+            })?;
 
-    wait_until_statefulset_scaled(
-        ctx.client.clone(),
-        &ctx.config.namespace,
+        wait_until_statefulset_scaled(
+            ctx.client.clone(),
+            &ctx.config.namespace,
+            proxy_sts_name,
+            1,
+            &PollingConfig {
+                initial_wait: Duration::from_secs(10),
+                poll_interval: Duration::from_secs(10),
+                max_wait: Duration::from_mins(15),
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(|e| StatefulSetScaleError::StatefulSetNotScaled(proxy_sts_name.to_string(), e))
+        .map_err(|e| DailyRoutineError::RelaunchMinecraftServer(proxy_sts_name.to_string(), e))
+    }
+    .await;
+    let result = ctx.diagnose_statefulset(result, proxy_sts_name).await;
+    ctx.record_statefulset_event(
         proxy_sts_name,
-        1,
-        &PollingConfig {
-            initial_wait: Duration::from_secs(10),
-            poll_interval: Duration::from_secs(10),
-            max_wait: Duration::from_mins(15),
-            ..Default::default()
-        },
+        RoutineAction::Relaunch,
+        result.as_ref().map(|_| relaunch_note(&ctx)),
     )
-    .await
-    .map_err(|e| StatefulSetScaleError::StatefulSetNotScaled(proxy_sts_name.to_string(), e))
-    .map_err(|e| DailyRoutineError::RelaunchMinecraftServer(proxy_sts_name.to_string(), e))?;
+    .await;
+    result?;
 
     info!("Phase 'relaunch_mcproxy' completed. Sleeping for 10 seconds before continuing...");
     tokio::time::sleep(Duration::from_secs(10)).await;
@@ -3,6 +3,7 @@ use std::time::Duration;
 use tracing::{Instrument, error, info, instrument, trace_span};
 
 use crate::config::polling::PollingConfig;
+use crate::kubernetes_objects::event_recorder::RoutineAction;
 use crate::kubernetes_objects::minecraft_chart::WeakMinecraftChart;
 use crate::kubernetes_objects::statefulset::{
     StatefulSetScaleError, scale_statefulset_to_zero, wait_until_statefulset_scaled,
@@ -54,11 +55,18 @@ async fn relaunch_mcserver(
             )
             .await
             .map_err(|e| StatefulSetScaleError::StatefulSetNotScaled(sts_name.clone(), e))
-            .map_err(|e| DailyRoutineError::ShutdownMinecraftServer(sts_name.clone(), e))?;
+            .map_err(|e| DailyRoutineError::RelaunchMinecraftServer(sts_name.clone(), e))?;
 
             Ok(())
         }
         .await;
+        let result = ctx.diagnose_statefulset(result, sts_name).await;
+        ctx.record_statefulset_event(
+            sts_name,
+            RoutineAction::Relaunch,
+            result.as_ref().map(|_| relaunch_note(&ctx)),
+        )
+        .await;
 
         result
             .inspect(|_| {
@@ -79,6 +87,13 @@ async fn relaunch_mcserver(
     .await
 }
 
+pub(super) fn relaunch_note(ctx: &DailyRoutineContext) -> String {
+    format!(
+        "Scaled up to 1 replica and ready again, by man10routine run {}",
+        ctx.run_id
+    )
+}
+
 pub(crate) fn task_relaunch_mcserver(
     ctx: DailyRoutineContext,
     mcserver: WeakMinecraftChart,
@@ -6,30 +6,17 @@ use std::time::Duration;
 
 use tracing::{info, instrument};
 
-use crate::kubernetes_objects::statefulset::{
-    StatefulSetScaleError, scale_statefulset_to_zero, wait_until_statefulset_scaled,
-};
 use crate::routine::daily::error::DailyRoutineError;
 
+use super::phase_shutdown_mcservers::shutdown_minecraft_chart;
+
 #[instrument(name = "phase_shutdown_mcproxy", skip(ctx))]
 async fn phase_shutdown_mcproxy(ctx: DailyRoutineContext) -> Result<(), DailyRoutineError> {
-    let proxy_sts_name = &ctx.config.mcproxy.read().await.name;
+    let mcproxy = ctx.config.mcproxy.read().await;
     info!("Stopping proxy server...");
-    let scaled =
-        scale_statefulset_to_zero(ctx.client.clone(), &ctx.config.namespace, proxy_sts_name, 0)
-            .await
-            .map_err(|e| {
-                DailyRoutineError::ShutdownMinecraftServer(proxy_sts_name.to_string(), e)
-            })?;
-    if !scaled {
-        return Ok(());
-    }
-
-    wait_until_statefulset_scaled(
-        ctx.client.clone(),
-        &ctx.config.namespace,
-        proxy_sts_name,
-        0,
+    let scaled = shutdown_minecraft_chart(
+        &ctx,
+        &mcproxy,
         &PollingConfig {
             initial_wait: Duration::from_secs(60),
             poll_interval: Duration::from_secs(5),
@@ -37,9 +24,10 @@ async fn phase_shutdown_mcproxy(ctx: DailyRoutineContext) -> Result<(), DailyRou
             ..Default::default()
         },
     )
-    .await
-    .map_err(|e| StatefulSetScaleError::StatefulSetNotScaled(proxy_sts_name.to_string(), e))
-    .map_err(|e| DailyRoutineError::ShutdownMinecraftServer(proxy_sts_name.to_string(), e))?;
+    .await?;
+    if !scaled {
+        return Ok(());
+    }
 
     info!("Phase 'shutdown_mcproxy' completed. Sleeping for 10 seconds before continuing...");
     tokio::time::sleep(Duration::from_secs(10)).await;
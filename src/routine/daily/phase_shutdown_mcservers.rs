@@ -8,7 +8,8 @@ use tracing::{info, instrument};
 
 use crate::config::polling::PollingConfig;
 use crate::error::SpannedExt;
-use crate::kubernetes_objects::minecraft_chart::WeakMinecraftChart;
+use crate::kubernetes_objects::event_recorder::RoutineAction;
+use crate::kubernetes_objects::minecraft_chart::{MinecraftChart, WeakMinecraftChart};
 use crate::kubernetes_objects::statefulset::{
     StatefulSetScaleError, scale_statefulset_to_zero, wait_until_statefulset_scaled,
 };
@@ -22,7 +23,6 @@ async fn shutdown_mcserver(
     ctx: DailyRoutineContext,
     mcserver: WeakMinecraftChart,
 ) -> Result<(), DailyRoutineError> {
-    let client = ctx.client.clone();
     let namespace = ctx.config.namespace.clone();
 
     let mcserver = mcserver.upgrade().expect("MinecraftChart has been dropped");
@@ -31,36 +31,84 @@ async fn shutdown_mcserver(
     let (mcserver_name, sts_name, rcon_container) =
         { (&read.name, &read.name, &read.rcon_container) };
 
-    let pod_name = format!("{sts_name}-0");
-
     let span = trace_span!(
         "shutdown_mcserver",
         kubernetes_namespace = %namespace,
         statefulset_name = %sts_name,
-        pod_name = %pod_name,
         mcserver_name = %mcserver_name,
         rcon_container = %rcon_container,
     );
 
-    async move {
-        let result: Result<(), DailyRoutineError> = async {
-            let scaled = scale_statefulset_to_zero(client.clone(), &namespace, sts_name, 0)
-                .await
-                .map_err(|e| {
-                    DailyRoutineError::ShutdownMinecraftServer(sts_name.clone(), e)
-                })?;
+    async {
+        let result = shutdown_minecraft_chart(
+            &ctx,
+            &read,
+            &PollingConfig {
+                initial_wait: Duration::from_secs(5),
+                poll_interval: Duration::from_secs(5),
+                max_wait: Duration::from_mins(5),
+                ..Default::default()
+            },
+        )
+        .await
+        .map(|_| ());
 
-            if !scaled {
-                return Ok(());
-            }
+        result
+            .inspect(|_| {
+                info!("Phase 'shutdown_mcserver' for mcserver '{mcserver_name}' completed.");
+            })
+            .inspect_err(|e| {
+                error!(
+                    "Phase 'shutdown_mcserver' for mcserver '{mcserver_name}' failed: {}",
+                    e
+                );
+            })
+    }
+    .instrument(span)
+    .await
+}
 
-            let pod_api: Api<Pod> = Api::namespaced(client.clone(), &namespace);
+/// Scales the chart's StatefulSet to zero, sends its stop command and waits for the pod to be gone.
+///
+/// Returns `false` if the StatefulSet was already scaled down.
+pub(super) async fn shutdown_minecraft_chart(
+    ctx: &DailyRoutineContext,
+    chart: &MinecraftChart,
+    polling_config: &PollingConfig,
+) -> Result<bool, DailyRoutineError> {
+    let client = ctx.client.clone();
+    let namespace = ctx.config.namespace.as_str();
+    let sts_name = &chart.name;
+    let pod_name = format!("{sts_name}-0");
+
+    let scaled = scale_statefulset_to_zero(client.clone(), namespace, sts_name, 0)
+        .await
+        .map_err(|e| DailyRoutineError::ShutdownMinecraftServer(sts_name.clone(), e));
+    if let Ok(false) = scaled {
+        return Ok(false);
+    }
+    ctx.record_statefulset_event(
+        sts_name,
+        RoutineAction::ScaleDown,
+        scaled.as_ref().map(|_| {
+            format!(
+                "Scaled down to 0 replicas by man10routine run {}",
+                ctx.run_id
+            )
+        }),
+    )
+    .await;
+    scaled?;
+
+    if let Some(stop_command) = chart.stop.argv() {
+        async {
+            let pod_api: Api<Pod> = Api::namespaced(client.clone(), namespace);
 
             let exec_result = pod_api
                 .exec(
                     &pod_name,
-                    ["rcon-cli", "stop"],
-                    &AttachParams::default().container(rcon_container),
+                    stop_command,
+                    &AttachParams::default().container(&chart.rcon_container),
                 )
                 .await;
 
@@ -69,71 +117,64 @@ async fn shutdown_mcserver(
                     if let Err(e) = attached
                         .join()
                         .await
-                        .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync + 'static>)
+                        .map_err(|e| {
+                            Box::new(e) as Box<dyn std::error::Error + Send + Sync + 'static>
+                        })
                         .with_span_trace()
                         .map_err(StatefulSetScaleError::Exec)
-                        .map_err(|e| DailyRoutineError::ShutdownMinecraftServer(sts_name.clone(), e))
+                        .map_err(|e| {
+                            DailyRoutineError::ShutdownMinecraftServer(sts_name.clone(), e)
+                        })
                     {
                         warn!(
-                            "Failed to join executed stop command on mcserver '{mcserver_name}' (pod '{}'): {}",
-                            pod_name,
-                            e
+                            "Failed to join executed stop command on '{sts_name}' (pod '{}'): {}",
+                            pod_name, e
                         );
                     }
                 }
                 Err(e) => {
                     warn!(
-                        "Failed to exec stop command on mcserver '{mcserver_name}' (pod '{}'): {}",
-                        pod_name,
-                        e
+                        "Failed to exec stop command on '{sts_name}' (pod '{}'): {}",
+                        pod_name, e
                     );
                 }
             }
-
-            wait_until_statefulset_scaled(
-                client.clone(),
-                &namespace,
-                sts_name,
-                0,
-                &PollingConfig {
-                    initial_wait: Duration::from_secs(5),
-                    poll_interval: Duration::from_secs(5),
-                    max_wait: Duration::from_mins(5),
-                    ..Default::default()
-                }
-            )
-            .await
-                .map_err(|e| {
-                    StatefulSetScaleError::StatefulSetNotScaled(sts_name.clone(), e)
-                })
-                .map_err(|e| DailyRoutineError::ShutdownMinecraftServer(sts_name.clone(), e))?;
-
-            Ok(())
         }
+        .instrument(trace_span!(
+            "exec_stop_command",
+            pod_name = %pod_name,
+            stop_method = %chart.stop.description(),
+        ))
         .await;
-
-        result
-            .inspect(|_| {
-                info!("Phase 'shutdown_mcserver' for mcserver '{mcserver_name}' completed.");
-            })
-            .inspect_err(|e| {
-                error!(
-                    "Phase 'shutdown_mcserver' for mcserver '{mcserver_name}' failed: {}",
-                    e
-                );
-            })
     }
-    .instrument(span)
-    .await
+
+    let result = wait_until_statefulset_scaled(client, namespace, sts_name, 0, polling_config)
+        .await
+        .map_err(|e| StatefulSetScaleError::StatefulSetNotScaled(sts_name.clone(), e))
+        .map_err(|e| DailyRoutineError::ShutdownMinecraftServer(sts_name.clone(), e));
+    let result = ctx.diagnose_statefulset(result, sts_name).await;
+    ctx.record_statefulset_event(
+        sts_name,
+        RoutineAction::Stop,
+        result.as_ref().map(|_| {
+            format!(
+                "Server stopped {} and its pod is gone",
+                chart.stop.description()
+            )
+        }),
+    )
+    .await;
+    result?;
+
+    Ok(true)
 }
 
 pub(crate) fn task_shutdown_mcserver(
     task_name: String,
+    deps: Vec<String>,
     mcserver: WeakMinecraftChart,
 ) -> TaskSpec<DailyRoutineContext, DailyRoutineError> {
-    TaskSpec::new(
-        task_name,
-        vec!["shutdown_mcproxy".to_string()],
-        move |ctx| Box::pin(async move { shutdown_mcserver(ctx, mcserver).await }),
-    )
+    TaskSpec::new(task_name, deps, move |ctx| {
+        Box::pin(async move { shutdown_mcserver(ctx, mcserver).await })
+    })
 }
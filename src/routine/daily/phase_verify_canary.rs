@@ -0,0 +1,151 @@
+use k8s_openapi::api::core::v1::Pod;
+use kube::{Api, Client};
+use thiserror::Error;
+use tracing::{Instrument, error, info, instrument, trace_span};
+
+use crate::error::{SpannedErr, SpannedExt};
+use crate::kubernetes_objects::pod::{PodExecError, exec_in_pod};
+use crate::scheduler::TaskFuture;
+
+use super::DailyRoutineContext;
+use super::error::DailyRoutineError;
+
+#[derive(Error, Debug)]
+pub enum CanaryCheckError {
+    #[error("Kubernetes client error: {0}")]
+    KubeClient(#[from] kube::Error),
+
+    #[error("Pod '{0}' is not ready")]
+    PodNotReady(String),
+
+    #[error("Pod '{pod_name}' restarted {restarts} times while waiting for it to become stable")]
+    PodRestarted { pod_name: String, restarts: i32 },
+
+    #[error("Smoke command cannot be executed: {0}")]
+    SmokeCommandExec(#[from] PodExecError),
+
+    #[error("Smoke command failed: {message}\n{stderr}")]
+    SmokeCommandFailed { message: String, stderr: String },
+}
+
+#[instrument("phase_verify_canary", skip_all)]
+async fn verify_canary(ctx: DailyRoutineContext) -> Result<(), DailyRoutineError> {
+    let canary = ctx
+        .config
+        .canary
+        .as_ref()
+        .expect("verify_canary task requires canary configuration");
+    let mcserver = ctx
+        .config
+        .mcservers
+        .get(&canary.mcserver)
+        .expect("canary mcserver must exist")
+        .read()
+        .await;
+    let pod_name = format!("{}-0", mcserver.name);
+
+    let span = trace_span!(
+        "verify_canary",
+        kubernetes_namespace = %ctx.config.namespace,
+        mcserver_name = %mcserver.name,
+        pod_name = %pod_name,
+    );
+
+    async {
+        let initial_restarts =
+            check_pod_ready(ctx.client.clone(), &ctx.config.namespace, &pod_name).await?;
+
+        info!(
+            "Canary '{}' is ready. Waiting {} seconds to confirm it stays stable...",
+            mcserver.name,
+            canary.stable_for.as_secs()
+        );
+        tokio::time::sleep(canary.stable_for).await;
+
+        let restarts =
+            check_pod_ready(ctx.client.clone(), &ctx.config.namespace, &pod_name).await?;
+        if restarts != initial_restarts {
+            return Err(CanaryCheckError::PodRestarted {
+                pod_name: pod_name.clone(),
+                restarts: restarts - initial_restarts,
+            })
+            .with_span_trace();
+        }
+
+        if let Some(smoke_command) = &canary.smoke_command {
+            let output = exec_in_pod(
+                ctx.client.clone(),
+                &ctx.config.namespace,
+                &pod_name,
+                &mcserver.rcon_container,
+                smoke_command.clone(),
+            )
+            .await
+            .map_err(CanaryCheckError::from)
+            .with_span_trace()?;
+            if let Some(message) = output.failure_message() {
+                return Err(CanaryCheckError::SmokeCommandFailed {
+                    message,
+                    stderr: output.stderr,
+                })
+                .with_span_trace();
+            }
+            info!(
+                "Smoke command on canary succeeded: {}",
+                output.stdout.trim()
+            );
+        }
+
+        Ok(())
+    }
+    .instrument(span)
+    .await
+    .map_err(|e| DailyRoutineError::CanaryVerification(mcserver.name.clone(), e))
+    .inspect(|_| {
+        info!(
+            "Canary '{}' verified. Relaunching the remaining mcservers...",
+            mcserver.name
+        );
+    })
+    .inspect_err(|e| {
+        error!(
+            "Canary '{}' failed verification; the remaining mcservers will not be relaunched: {}",
+            mcserver.name, e
+        );
+    })
+}
+
+/// Returns the total restart count of the pod's containers if the pod is ready.
+async fn check_pod_ready(
+    client: Client,
+    namespace: &str,
+    pod_name: &str,
+) -> Result<i32, SpannedErr<CanaryCheckError>> {
+    let pod_api: Api<Pod> = Api::namespaced(client, namespace);
+    let pod = pod_api
+        .get(pod_name)
+        .await
+        .map_err(CanaryCheckError::from)
+        .with_span_trace()?;
+    let status = pod.status.unwrap_or_default();
+
+    let ready = status
+        .conditions
+        .iter()
+        .flatten()
+        .any(|c| c.type_ == "Ready" && c.status == "True");
+    if !ready {
+        return Err(CanaryCheckError::PodNotReady(pod_name.to_string())).with_span_trace();
+    }
+
+    Ok(status
+        .container_statuses
+        .iter()
+        .flatten()
+        .map(|c| c.restart_count)
+        .sum())
+}
+
+pub(crate) fn task_verify_canary(ctx: DailyRoutineContext) -> TaskFuture<DailyRoutineError> {
+    Box::pin(verify_canary(ctx))
+}
@@ -0,0 +1,677 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::MutexGuard;
+
+use k8s_openapi::chrono::{DateTime, Utc};
+use serde::Serialize;
+use thiserror::Error;
+use tracing::{error, info, instrument};
+use tracing_error::{ExtractSpanTrace, SpanTrace};
+
+use crate::config::notifications::NotificationKind;
+use crate::error::SpannedErr;
+use crate::history::{RunSummary, append_history};
+use crate::kubernetes_objects::configmap::apply_configmap_data;
+use crate::metrics::export_metrics;
+use crate::notification::Notification;
+use crate::scheduler::TaskSpec;
+
+use super::DailyRoutineContext;
+use super::error::DailyRoutineError;
+
+/// Key of the report in the ConfigMap given by `--report-configmap`
+pub(crate) const REPORT_CONFIGMAP_KEY: &str = "report.json";
+
+/// Destinations of the run report
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReportOutput {
+    /// File to which the report is written
+    pub(crate) path: Option<PathBuf>,
+
+    /// ConfigMap in the config's namespace to which the report is written
+    pub(crate) configmap: Option<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum ReportError {
+    #[error("Report cannot be serialized: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    #[error("Report cannot be written to {0}: {1}")]
+    WriteFile(PathBuf, std::io::Error),
+
+    #[error("Report cannot be written to ConfigMap '{0}': {1}")]
+    WriteConfigMap(String, SpannedErr<kube::Error>),
+}
+
+/// What happened during a run, collected while the tasks are running
+#[derive(Debug, Default)]
+pub(crate) struct RunRecorder {
+    tasks: BTreeMap<String, TaskRecord>,
+    jobs: Vec<JobReport>,
+    errors: Vec<ErrorReport>,
+    torn_down: BTreeSet<String>,
+    restored: BTreeSet<String>,
+    held: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TaskRecord {
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+    succeeded: bool,
+    skipped: bool,
+
+    /// Error with which the task failed, alerted once the scheduler has stopped
+    error: Option<String>,
+}
+
+/// Structured summary of a run, written at the end of every run
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct RunReport {
+    pub(crate) run_id: String,
+    pub(crate) routine: String,
+    pub(crate) started_at: DateTime<Utc>,
+    pub(crate) finished_at: DateTime<Utc>,
+    pub(crate) succeeded: bool,
+    pub(crate) tasks: Vec<TaskReport>,
+    pub(crate) errors: Vec<ErrorReport>,
+    pub(crate) argocd_applications: Vec<ArgoCdReport>,
+    pub(crate) jobs: Vec<JobReport>,
+    pub(crate) downtime: Vec<DowntimeReport>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct TaskReport {
+    pub(crate) name: String,
+    pub(crate) status: TaskStatus,
+    pub(crate) started_at: Option<DateTime<Utc>>,
+    pub(crate) finished_at: Option<DateTime<Utc>>,
+    pub(crate) duration_seconds: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TaskStatus {
+    Succeeded,
+    Failed,
+
+    /// Succeeded without running its job, whose `when` condition did not match the run date
+    Skipped,
+
+    /// Started, but aborted because another task failed
+    Cancelled,
+
+    /// Never started because another task failed
+    NotStarted,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct ErrorReport {
+    /// What failed, such as `routine`, `job/<chart>/<job>` or `release/<chart>`
+    pub(crate) source: String,
+    pub(crate) message: String,
+    pub(crate) span_trace: Option<String>,
+    pub(crate) diagnostics: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct ArgoCdReport {
+    pub(crate) application: String,
+    pub(crate) restored: bool,
+
+    /// Released with automated sync left disabled because the canary failed verification
+    pub(crate) held_after_canary_failure: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct JobReport {
+    /// Name of the created Kubernetes Job
+    pub(crate) name: String,
+    pub(crate) chart_name: String,
+    pub(crate) job_name: String,
+    pub(crate) attempt: u32,
+    pub(crate) succeeded: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct DowntimeReport {
+    /// `mcproxy` or the name of the mcserver in the config
+    pub(crate) server: String,
+
+    /// Internal name of the chart
+    pub(crate) chart_name: String,
+    pub(crate) down_at: DateTime<Utc>,
+
+    /// Time at which the relaunch finished, if it did
+    pub(crate) up_at: Option<DateTime<Utc>>,
+
+    /// Until `up_at`, or until the end of the run if the server was not relaunched
+    pub(crate) downtime_seconds: f64,
+}
+
+impl ErrorReport {
+    pub(crate) fn new(
+        source: impl Into<String>,
+        message: impl ToString,
+        span_trace: Option<&SpanTrace>,
+    ) -> ErrorReport {
+        ErrorReport {
+            source: source.into(),
+            message: message.to_string(),
+            span_trace: span_trace.map(|s| s.to_string()),
+            diagnostics: None,
+        }
+    }
+
+    pub(crate) fn from_routine_error(
+        source: impl Into<String>,
+        error: &DailyRoutineError,
+    ) -> ErrorReport {
+        ErrorReport {
+            diagnostics: error.diagnostics().map(|d| d.to_string()),
+            ..ErrorReport::new(source, error, error.span_trace())
+        }
+    }
+}
+
+impl RunRecorder {
+    pub(crate) fn record_job(&mut self, job: JobReport) {
+        self.jobs.push(job);
+    }
+
+    pub(crate) fn record_error(&mut self, error: ErrorReport) {
+        self.errors.push(error);
+    }
+
+    pub(crate) fn record_teardown(&mut self, application: String) {
+        self.torn_down.insert(application);
+    }
+
+    pub(crate) fn record_restore(&mut self, application: String) {
+        self.restored.insert(application);
+    }
+
+    pub(crate) fn record_hold(&mut self, application: String) {
+        self.held.insert(application);
+    }
+
+    pub(crate) fn was_torn_down(&self, application: &str) -> bool {
+        self.torn_down.contains(application)
+    }
+
+    pub(crate) fn task_status(&self, name: &str) -> Option<TaskStatus> {
+        let task = self.tasks.get(name)?;
+        Some(match (task.started_at, task.finished_at) {
+            (None, _) => TaskStatus::NotStarted,
+            (Some(_), None) => TaskStatus::Cancelled,
+            (Some(_), Some(_)) if task.succeeded && task.skipped => TaskStatus::Skipped,
+            (Some(_), Some(_)) if task.succeeded => TaskStatus::Succeeded,
+            (Some(_), Some(_)) => TaskStatus::Failed,
+        })
+    }
+
+    fn task_reports(&self) -> Vec<TaskReport> {
+        let mut tasks: Vec<TaskReport> = self
+            .tasks
+            .iter()
+            .map(|(name, task)| TaskReport {
+                name: name.clone(),
+                status: self.task_status(name).expect("task is recorded"),
+                started_at: task.started_at,
+                finished_at: task.finished_at,
+                duration_seconds: task
+                    .started_at
+                    .zip(task.finished_at)
+                    .map(|(start, end)| seconds(start, end)),
+            })
+            .collect();
+        // Tasks in the order they started, those never started last
+        tasks.sort_by_key(|task| (task.started_at.is_none(), task.started_at));
+        tasks
+    }
+
+    /// Downtime from the start of the shutdown task to the end of the relaunch task
+    fn downtime(
+        &self,
+        server: &str,
+        chart_name: &str,
+        shutdown_task: &str,
+        relaunch_task: &str,
+        finished_at: DateTime<Utc>,
+    ) -> Option<DowntimeReport> {
+        let down_at = self.tasks.get(shutdown_task)?.started_at?;
+        let up_at = self
+            .tasks
+            .get(relaunch_task)
+            .filter(|task| task.succeeded)
+            .and_then(|task| task.finished_at);
+        Some(DowntimeReport {
+            server: server.to_string(),
+            chart_name: chart_name.to_string(),
+            down_at,
+            up_at,
+            downtime_seconds: seconds(down_at, up_at.unwrap_or(finished_at)),
+        })
+    }
+}
+
+/// Final message of the run, listing the failed tasks and the downtime of each server
+fn summary_notification(report: &RunReport) -> Notification {
+    let outcome = if report.succeeded {
+        "succeeded"
+    } else {
+        "failed"
+    };
+    let seconds = (report.finished_at - report.started_at).num_seconds();
+    let mut text = format!("Finished in {}m{:02}s.", seconds / 60, seconds % 60);
+
+    let failed: Vec<&str> = report
+        .tasks
+        .iter()
+        .filter(|t| matches!(t.status, TaskStatus::Failed | TaskStatus::Cancelled))
+        .map(|t| t.name.as_str())
+        .chain(
+            report
+                .errors
+                .iter()
+                .filter(|e| e.source.starts_with("job/"))
+                .map(|e| e.source.as_str()),
+        )
+        .collect();
+    if !failed.is_empty() {
+        text.push_str(&format!("\nFailed: {}", failed.join(", ")));
+    }
+    let skipped: Vec<&str> = report
+        .tasks
+        .iter()
+        .filter(|t| t.status == TaskStatus::Skipped)
+        .map(|t| t.name.as_str())
+        .collect();
+    if !skipped.is_empty() {
+        text.push_str(&format!("\nSkipped: {}", skipped.join(", ")));
+    }
+    if let Some(error) = report.errors.iter().find(|e| e.source == "routine") {
+        text.push_str(&format!("\nError: {}", error.message));
+    }
+    let held: Vec<&str> = report
+        .argocd_applications
+        .iter()
+        .filter(|a| a.held_after_canary_failure)
+        .map(|a| a.application.as_str())
+        .collect();
+    if !held.is_empty() {
+        text.push_str(&format!(
+            "\nAutomated sync left disabled after the canary failure: {}",
+            held.join(", ")
+        ));
+    }
+    for downtime in &report.downtime {
+        let state = if downtime.up_at.is_some() {
+            ""
+        } else {
+            " (still down)"
+        };
+        text.push_str(&format!(
+            "\n{}: down for {:.0}s{}",
+            downtime.server, downtime.downtime_seconds, state
+        ));
+    }
+
+    Notification {
+        kind: NotificationKind::Summary,
+        run_id: report.run_id.clone(),
+        title: format!("{} routine {}", capitalize(&report.routine), outcome),
+        text,
+        succeeded: Some(report.succeeded),
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+fn seconds(start: DateTime<Utc>, end: DateTime<Utc>) -> f64 {
+    (end - start).num_milliseconds() as f64 / 1000.0
+}
+
+impl DailyRoutineContext {
+    pub(crate) fn recorder(&self) -> MutexGuard<'_, RunRecorder> {
+        self.recorder.lock().expect("run recorder lock poisoned")
+    }
+
+    /// Records that the task succeeded without running its job, for the report and the progress
+    /// view.
+    pub(crate) fn record_skip(&self, task_name: &str) {
+        self.recorder()
+            .tasks
+            .entry(task_name.to_string())
+            .or_default()
+            .skipped = true;
+        if let Some(progress) = &self.progress {
+            progress.skip(task_name);
+        }
+    }
+
+    /// Wraps the task so that its start, end and result are recorded for the report.
+    pub(super) fn track_task(
+        &self,
+        task: TaskSpec<DailyRoutineContext, DailyRoutineError>,
+    ) -> TaskSpec<DailyRoutineContext, DailyRoutineError> {
+        self.recorder()
+            .tasks
+            .insert(task.name.clone(), TaskRecord::default());
+
+        let name = task.name.clone();
+        let exec = task.exec;
+        TaskSpec::new(task.name, task.deps, move |ctx: DailyRoutineContext| {
+            Box::pin(async move {
+                ctx.recorder()
+                    .tasks
+                    .entry(name.clone())
+                    .or_default()
+                    .started_at = Some(Utc::now());
+                let result = exec(ctx.clone()).await;
+                {
+                    let mut recorder = ctx.recorder();
+                    let task = recorder.tasks.entry(name.clone()).or_default();
+                    task.finished_at = Some(Utc::now());
+                    task.succeeded = result.is_ok();
+                    task.error = result.as_ref().err().map(|e| e.to_string());
+                }
+                result
+            })
+        })
+    }
+
+    /// Alerts the failure of each task. Called once the scheduler has stopped so that a slow sink
+    /// does not hold back the cancellation of the other tasks.
+    pub(crate) async fn alert_failed_tasks(&self) {
+        let failed: Vec<(String, String)> = self
+            .recorder()
+            .tasks
+            .iter()
+            .filter_map(|(name, task)| Some((name.clone(), task.error.clone()?)))
+            .collect();
+        for (name, error) in failed {
+            self.notifications
+                .alert(&self.run_id, format!("Task {name} failed"), error)
+                .await;
+        }
+    }
+
+    /// Builds the report of the run which ended with `result`.
+    pub(crate) async fn build_report(
+        &self,
+        routine: &str,
+        result: &Result<(), DailyRoutineError>,
+    ) -> RunReport {
+        let finished_at = Utc::now();
+
+        let mut charts = vec![(
+            "mcproxy".to_string(),
+            self.config.mcproxy.clone(),
+            "shutdown_mcproxy".to_string(),
+            "relaunch_mcproxy".to_string(),
+        )];
+        charts.extend(self.config.mcservers.iter().map(|(name, mcserver)| {
+            (
+                name.clone(),
+                mcserver.clone(),
+                format!("shutdown_mcserver/{name}"),
+                format!("relaunch_mcserver/{name}"),
+            )
+        }));
+
+        let mut chart_names = Vec::new();
+        for (_, chart, _, _) in &charts {
+            chart_names.push(chart.read().await.name.clone());
+        }
+
+        let recorder = self.recorder();
+
+        let downtime = charts
+            .iter()
+            .zip(&chart_names)
+            .filter_map(|((server, _, shutdown_task, relaunch_task), chart_name)| {
+                recorder.downtime(
+                    server,
+                    chart_name,
+                    shutdown_task,
+                    relaunch_task,
+                    finished_at,
+                )
+            })
+            .collect();
+
+        let mut errors = recorder.errors.clone();
+        if let Err(e) = result {
+            errors.insert(0, ErrorReport::from_routine_error("routine", e));
+        }
+
+        RunReport {
+            run_id: self.run_id.clone(),
+            routine: routine.to_string(),
+            started_at: self.started_at,
+            finished_at,
+            succeeded: result.is_ok(),
+            tasks: recorder.task_reports(),
+            errors,
+            argocd_applications: recorder
+                .torn_down
+                .iter()
+                .map(|application| ArgoCdReport {
+                    application: application.clone(),
+                    restored: recorder.restored.contains(application),
+                    held_after_canary_failure: recorder.held.contains(application),
+                })
+                .collect(),
+            jobs: recorder.jobs.clone(),
+            downtime,
+        }
+    }
+
+    /// Builds the report of the run, writes it to `output`, exports its metrics and adds it to
+    /// the run history, logging any failure.
+    #[instrument("report", skip(self, result, output))]
+    pub(crate) async fn report(
+        &self,
+        routine: &str,
+        result: &Result<(), DailyRoutineError>,
+        output: &ReportOutput,
+    ) {
+        let history = self.config.history.as_ref();
+        let metrics = self.config.metrics.as_ref();
+        if output.path.is_none()
+            && output.configmap.is_none()
+            && history.is_none()
+            && metrics.is_none()
+            && self.notifications.is_empty()
+        {
+            return;
+        }
+
+        let report = self.build_report(routine, result).await;
+        self.notifications
+            .notify(summary_notification(&report))
+            .await;
+        if let Err(e) = self.write_report(&report, output).await {
+            error!("Failed to write the run report: {}", e);
+        }
+        if let Some(metrics) = metrics
+            && let Err(e) = export_metrics(metrics, &report).await
+        {
+            error!("Failed to export the run metrics: {}", e);
+        }
+
+        if let Some(history) = history {
+            let summary = RunSummary::from_report(&report);
+            match append_history(
+                self.client.clone(),
+                &self.config.namespace,
+                history,
+                summary,
+            )
+            .await
+            {
+                Ok(()) => info!(
+                    "Run added to the history in ConfigMap '{}'.",
+                    history.configmap
+                ),
+                Err(e) => error!("Failed to add the run to the history: {}", e),
+            }
+        }
+    }
+
+    async fn write_report(
+        &self,
+        report: &RunReport,
+        output: &ReportOutput,
+    ) -> Result<(), ReportError> {
+        let json = serde_json::to_string_pretty(report)?;
+
+        if let Some(path) = &output.path {
+            tokio::fs::write(path, &json)
+                .await
+                .map_err(|e| ReportError::WriteFile(path.clone(), e))?;
+            info!("Run report written to {}.", path.display());
+        }
+
+        if let Some(name) = &output.configmap {
+            apply_configmap_data(
+                self.client.clone(),
+                &self.config.namespace,
+                name,
+                REPORT_CONFIGMAP_KEY,
+                json,
+            )
+            .await
+            .map_err(|e| ReportError::WriteConfigMap(name.clone(), e))?;
+            info!("Run report written to ConfigMap '{}'.", name);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_reports_and_downtime() {
+        let at = |seconds: i64| DateTime::from_timestamp(1_800_000_000 + seconds, 0).unwrap();
+        let mut recorder = RunRecorder::default();
+        let mut record = |name: &str, started: Option<i64>, finished: Option<i64>, ok: bool| {
+            recorder.tasks.insert(
+                name.to_string(),
+                TaskRecord {
+                    started_at: started.map(at),
+                    finished_at: finished.map(at),
+                    succeeded: ok,
+                    skipped: false,
+                    error: None,
+                },
+            );
+        };
+        record("shutdown_mcserver/lobby", Some(0), Some(30), true);
+        record("relaunch_mcserver/lobby", Some(100), Some(160), true);
+        record("shutdown_mcserver/survival", Some(5), Some(20), true);
+        record("relaunch_mcserver/survival", Some(40), None, false);
+        record("after_relaunch_mcserver/survival", None, None, false);
+        recorder.tasks.insert(
+            "execute_job/global/render-map".to_string(),
+            TaskRecord {
+                started_at: Some(at(1)),
+                finished_at: Some(at(1)),
+                succeeded: true,
+                skipped: true,
+                error: None,
+            },
+        );
+
+        let tasks = recorder.task_reports();
+        let statuses: Vec<(&str, TaskStatus)> =
+            tasks.iter().map(|t| (t.name.as_str(), t.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("shutdown_mcserver/lobby", TaskStatus::Succeeded),
+                ("execute_job/global/render-map", TaskStatus::Skipped),
+                ("shutdown_mcserver/survival", TaskStatus::Succeeded),
+                ("relaunch_mcserver/survival", TaskStatus::Cancelled),
+                ("relaunch_mcserver/lobby", TaskStatus::Succeeded),
+                ("after_relaunch_mcserver/survival", TaskStatus::NotStarted),
+            ]
+        );
+        assert_eq!(tasks[0].duration_seconds, Some(30.0));
+
+        let lobby = recorder
+            .downtime(
+                "lobby",
+                "mcserver-lobby",
+                "shutdown_mcserver/lobby",
+                "relaunch_mcserver/lobby",
+                at(200),
+            )
+            .unwrap();
+        assert_eq!(lobby.up_at, Some(at(160)));
+        assert_eq!(lobby.downtime_seconds, 160.0);
+
+        let survival = recorder
+            .downtime(
+                "survival",
+                "mcserver-survival",
+                "shutdown_mcserver/survival",
+                "relaunch_mcserver/survival",
+                at(200),
+            )
+            .unwrap();
+        assert_eq!(survival.up_at, None);
+        assert_eq!(survival.downtime_seconds, 195.0);
+    }
+
+    #[test]
+    fn test_summary_lists_applications_held_after_canary_failure() {
+        let at = |seconds: i64| DateTime::from_timestamp(1_800_000_000 + seconds, 0).unwrap();
+        let application = |name: &str, held: bool| ArgoCdReport {
+            application: name.to_string(),
+            restored: !held,
+            held_after_canary_failure: held,
+        };
+        let report = RunReport {
+            run_id: "20270115-040000".to_string(),
+            routine: "daily".to_string(),
+            started_at: at(0),
+            finished_at: at(125),
+            succeeded: false,
+            tasks: vec![TaskReport {
+                name: "verify_canary".to_string(),
+                status: TaskStatus::Failed,
+                started_at: Some(at(60)),
+                finished_at: Some(at(120)),
+                duration_seconds: Some(60.0),
+            }],
+            errors: vec![],
+            argocd_applications: vec![
+                application("mcserver-lobby", false),
+                application("mcserver-survival", true),
+                application("mcproxy", true),
+            ],
+            jobs: vec![],
+            downtime: vec![],
+        };
+
+        let notification = summary_notification(&report);
+        assert_eq!(notification.title, "Daily routine failed");
+        assert_eq!(
+            notification.text,
+            "Finished in 2m05s.\nFailed: verify_canary\n\
+             Automated sync left disabled after the canary failure: mcserver-survival, mcproxy"
+        );
+    }
+}
@@ -2,8 +2,9 @@ use std::collections::{HashMap, VecDeque};
 
 use futures::future::BoxFuture;
 use thiserror::Error;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::task::JoinSet;
-use tracing::{Instrument, instrument, trace_span};
+use tracing::{Instrument, Span, instrument, trace_span};
 
 use crate::error::{SpannedErr, SpannedExt};
 
@@ -32,11 +33,19 @@ impl<TCtx, E> TaskSpec<TCtx, E> {
     }
 }
 
+/// Change of the state of a task, sent to the listener given to [`Scheduler::with_events`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskEvent {
+    Started(String),
+    Finished { name: String, succeeded: bool },
+}
+
 pub struct Scheduler<TCtx, E> {
     tasks: HashMap<String, TaskSpec<TCtx, E>>,
     reverse_edges: HashMap<String, Vec<String>>,
     indegree: HashMap<String, usize>,
     shutdown: Shutdown,
+    events: Option<UnboundedSender<TaskEvent>>,
 }
 
 #[derive(Error, Debug)]
@@ -92,10 +101,70 @@ where
             reverse_edges,
             indegree,
             shutdown,
+            events: None,
         })
     }
 
+    /// Sends the start and the end of each task to `events`.
+    pub fn with_events(mut self, events: UnboundedSender<TaskEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Tasks grouped by stage, sorted by name. Tasks without dependencies are in the first stage,
+    /// and the others one stage after their latest dependency.
+    pub fn stages(&self) -> Vec<Vec<String>> {
+        let mut indegree = self.indegree.clone();
+        let mut stage_of: HashMap<String, usize> = indegree
+            .iter()
+            .filter(|(_, deg)| **deg == 0)
+            .map(|(name, _)| (name.clone(), 0))
+            .collect();
+        let mut ready: VecDeque<String> = stage_of.keys().cloned().collect();
+
+        while let Some(name) = ready.pop_front() {
+            let stage = stage_of[&name];
+            for dependent_name in self.reverse_edges.get(&name).into_iter().flatten() {
+                let dependent_stage = stage_of.entry(dependent_name.clone()).or_default();
+                *dependent_stage = (*dependent_stage).max(stage + 1);
+                let entry = indegree
+                    .get_mut(dependent_name)
+                    .expect("indegree should exist for dependent task");
+                *entry -= 1;
+                if *entry == 0 {
+                    ready.push_back(dependent_name.clone());
+                }
+            }
+        }
+
+        // Tasks in a dependency cycle never become ready and are left out
+        let mut stages: Vec<Vec<String>> = Vec::new();
+        for (name, stage) in stage_of {
+            if indegree[&name] != 0 {
+                continue;
+            }
+            if stages.len() <= stage {
+                stages.resize_with(stage + 1, Vec::new);
+            }
+            stages[stage].push(name);
+        }
+        for stage in &mut stages {
+            stage.sort();
+        }
+        stages
+    }
+
+    fn notify(&self, event: TaskEvent) {
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+
+    /// Runs the tasks, each in a `flight_task` span which is a child of the caller's span.
     pub async fn run(mut self, ctx: TCtx) -> Result<Result<(), E>, tokio::task::JoinError> {
+        // Spawned tasks do not inherit the caller's span, so it is captured once and given
+        // explicitly to each task's span
+        let parent = Span::current();
         let mut ready: VecDeque<String> = self
             .indegree
             .iter()
@@ -116,14 +185,19 @@ where
 
                 let task_spec = self.tasks.remove(&task_name).expect("task must exist");
                 let exec = task_spec.exec;
-                let span = trace_span!("flight_task", task_name = %task_name);
+                let span = trace_span!(parent: &parent, "flight_task", task_name = %task_name);
                 let ctx = ctx.clone();
+                self.notify(TaskEvent::Started(task_name.clone()));
                 inflight.spawn(
                     async move {
                         let res = exec(ctx).await;
                         (task_name, res)
                     }
-                    .instrument(span.or_current()),
+                    .instrument(if span.is_disabled() {
+                        parent.clone()
+                    } else {
+                        span
+                    }),
                 );
             }
 
@@ -133,6 +207,10 @@ where
 
             match joined {
                 Ok((name, res)) => {
+                    self.notify(TaskEvent::Finished {
+                        name: name.clone(),
+                        succeeded: res.is_ok(),
+                    });
                     if let Err(e) = res {
                         return Ok(Err(e));
                     }
@@ -157,3 +235,66 @@ where
         Ok(Ok(()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn task(name: &str, deps: &[&str], succeeds: bool) -> TaskSpec<(), String> {
+        let deps: Vec<String> = deps.iter().map(|d| d.to_string()).collect();
+        let error = format!("{name} failed");
+        TaskSpec::new(name, deps, move |_| {
+            async move { if succeeds { Ok(()) } else { Err(error) } }.boxed()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_stages_and_task_events() {
+        let tasks = vec![
+            task("relaunch_mcproxy", &["relaunch_mcserver/lobby"], true),
+            task("argocd_teardown", &[], true),
+            task("shutdown_mcserver/lobby", &["argocd_teardown"], true),
+            task("shutdown_mcproxy", &["argocd_teardown"], true),
+            task(
+                "relaunch_mcserver/lobby",
+                &["shutdown_mcserver/lobby", "shutdown_mcproxy"],
+                false,
+            ),
+        ];
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+        let scheduler = Scheduler::from_tasks(tasks, Shutdown::new())
+            .unwrap()
+            .with_events(events_tx);
+
+        assert_eq!(
+            scheduler.stages(),
+            vec![
+                vec!["argocd_teardown"],
+                vec!["shutdown_mcproxy", "shutdown_mcserver/lobby"],
+                vec!["relaunch_mcserver/lobby"],
+                vec!["relaunch_mcproxy"],
+            ]
+        );
+
+        let result = scheduler.run(()).await.unwrap();
+        assert_eq!(result, Err("relaunch_mcserver/lobby failed".to_string()));
+
+        let mut events = Vec::new();
+        while let Some(event) = events_rx.recv().await {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 8);
+        assert_eq!(events[0], TaskEvent::Started("argocd_teardown".to_string()));
+        assert_eq!(
+            events.last(),
+            Some(&TaskEvent::Finished {
+                name: "relaunch_mcserver/lobby".to_string(),
+                succeeded: false,
+            })
+        );
+        assert!(!events.contains(&TaskEvent::Started("relaunch_mcproxy".to_string())));
+    }
+}
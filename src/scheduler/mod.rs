@@ -1,5 +1,5 @@
 pub mod dag_scheduler;
 pub mod shutdown;
 
-pub use dag_scheduler::{InvalidDagError, Scheduler, TaskFuture, TaskSpec};
+pub use dag_scheduler::{InvalidDagError, Scheduler, TaskEvent, TaskFuture, TaskSpec};
 pub use shutdown::Shutdown;
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_http::hyper::HyperClient;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig, WithHttpConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::runtime::Tokio;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::trace::span_processor_with_async_runtime::BatchSpanProcessor;
+use thiserror::Error;
+use tracing::{Level, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::http::https_connector;
+
+/// Time allowed for the collector to accept a batch of spans
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum TelemetryError {
+    #[error("OTLP exporter cannot be built: {0}")]
+    Exporter(#[from] opentelemetry_otlp::ExporterBuildError),
+}
+
+/// Export of the spans of the run to an OpenTelemetry collector over OTLP/HTTP
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+}
+
+impl Telemetry {
+    /// Starts exporting spans to `endpoint`, the base URL of the collector such as
+    /// `http://tempo.monitoring:4318`.
+    ///
+    /// `OTEL_SERVICE_NAME`, `OTEL_RESOURCE_ATTRIBUTES` and `OTEL_EXPORTER_OTLP_HEADERS` are
+    /// honored as usual. Must be called within the Tokio runtime.
+    pub fn new(endpoint: &str) -> Result<Telemetry, TelemetryError> {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .with_timeout(EXPORT_TIMEOUT)
+            .with_http_client(HyperClient::new(https_connector(), EXPORT_TIMEOUT, None))
+            .build()?;
+
+        let mut resource = Resource::builder().with_attribute(opentelemetry::KeyValue::new(
+            "service.version",
+            env!("CARGO_PKG_VERSION"),
+        ));
+        if std::env::var_os("OTEL_SERVICE_NAME").is_none() {
+            resource = resource.with_service_name(env!("CARGO_PKG_NAME"));
+        }
+
+        // The exports run on the Tokio runtime, which the blocking HTTP exports of the default
+        // batch processor's own thread would not be able to use
+        let provider = SdkTracerProvider::builder()
+            .with_span_processor(BatchSpanProcessor::builder(exporter, Tokio).build())
+            .with_resource(resource.build())
+            .build();
+        Ok(Telemetry { provider })
+    }
+
+    /// Layer sending the spans of this crate, including `trace` ones, and warnings of others
+    pub fn layer<S>(&self) -> impl Layer<S> + use<S>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer()
+            .with_tracer(self.provider.tracer(env!("CARGO_PKG_NAME")))
+            .with_filter(
+                Targets::new()
+                    .with_default(Level::WARN)
+                    .with_target(env!("CARGO_CRATE_NAME"), Level::TRACE),
+            )
+    }
+
+    /// Exports the remaining spans. Must be called before the process exits.
+    pub async fn shutdown(self) {
+        let provider = self.provider;
+        let result = tokio::task::spawn_blocking(move || provider.shutdown()).await;
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => eprintln!("Failed to export the remaining spans: {e}"),
+            Err(e) => eprintln!("Failed to export the remaining spans: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::tests::stand_in;
+    use tracing::{info_span, trace_span};
+    use tracing_subscriber::prelude::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spans_exported_on_shutdown() {
+        let (url, mut requests) = stand_in(200).await;
+        let telemetry = Telemetry::new(&url).unwrap();
+        let subscriber = tracing_subscriber::registry().with(telemetry.layer());
+        tracing::subscriber::with_default(subscriber, || {
+            let _root = info_span!("daily_routine").entered();
+            let _task = trace_span!("flight_task", task_name = "argocd_teardown").entered();
+        });
+
+        telemetry.shutdown().await;
+
+        let request = requests.recv().await.unwrap();
+        assert!(request.head.starts_with("POST /hook/v1/traces HTTP/1.1"));
+        assert!(
+            request
+                .head
+                .to_lowercase()
+                .contains("content-type: application/x-protobuf")
+        );
+        assert!(request.body.contains("flight_task"));
+        assert!(request.body.contains("daily_routine"));
+    }
+}